@@ -0,0 +1,425 @@
+//! Proves, verifies and inspects the example circuits from the command line.
+//!
+//! Exit codes: 0 on success, 1 when a proof or constraint check fails, and
+//! 2 for usage and I/O errors.
+
+use std::{ collections::HashMap, fs, path::PathBuf, process::ExitCode };
+
+use halo2::{
+  envelope::ProofEnvelope,
+  inputs::{ parse_field, CircuitInput, FibonacciInput, FieldElement, RangeInput },
+  keys::{ to_hex, vk_fingerprint, KeyStore },
+  prover::prove,
+  stats::{ CircuitStats, StatsError },
+};
+use halo2_proofs::{
+  dev::MockProver,
+  pasta::Fp,
+  plonk::Circuit,
+};
+
+const USAGE: &str = "\
+usage: halo2-cli <command> <circuit> [options]
+       halo2-cli <command> --input FILE [options]
+
+commands:
+  keygen   cache parameters and record key fingerprints (keys are
+           regenerated on every run)
+  prove    write a proof envelope to --out
+  verify   check the proof envelope in --proof
+  mock     check the constraints with MockProver
+  layout   render the circuit layout to --out (needs --features dev-graph)
+  stats    print columns, rows, minimal k and proof cost as JSON
+
+circuits:
+  fibonacci  --n N [--f0 A] [--f1 B] [--private] [--out-value F]
+  range      --range 2|4|8|16|256|65536 [--value V]
+             (--value is the private witness; keygen and verify skip it)
+
+Field elements are decimal, 0x-prefixed hex, or either negated.
+
+options:
+  --input FILE   circuit and parameters as .json or .toml
+  --keys DIR     parameter and fingerprint directory (default: keys)
+  --out FILE     output file; .json writes a JSON envelope
+  --proof FILE   envelope to verify, binary or JSON
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Command {
+  Keygen,
+  Prove,
+  Verify,
+  Mock,
+  Layout,
+  Stats,
+}
+
+impl Command {
+  fn parse(name: &str) -> Result<Self, String> {
+    match name {
+      "keygen" => Ok(Command::Keygen),
+      "prove" => Ok(Command::Prove),
+      "verify" => Ok(Command::Verify),
+      "mock" => Ok(Command::Mock),
+      "layout" => Ok(Command::Layout),
+      "stats" => Ok(Command::Stats),
+      _ => Err(format!("unknown command `{}`", name)),
+    }
+  }
+
+  /// Whether the command synthesizes the circuit with its witness rather
+  /// than only building keys from its shape.
+  fn needs_witness(self) -> bool {
+    matches!(self, Command::Prove | Command::Mock | Command::Stats)
+  }
+}
+
+/// `--key value` pairs and bare `--flag`s.
+#[derive(Debug, Default)]
+struct Options {
+  values: HashMap<String, String>,
+}
+
+impl Options {
+  fn parse(args: &[String]) -> Result<Self, String> {
+    let mut values = HashMap::new();
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+      let key = arg.strip_prefix("--").ok_or_else(|| format!("unexpected argument `{}`", arg))?;
+      let value = match args.peek() {
+        Some(next) if !next.starts_with("--") => args.next().unwrap().clone(),
+        _ => String::new(),
+      };
+      values.insert(key.to_string(), value);
+    }
+    Ok(Self { values })
+  }
+
+  fn get(&self, key: &str) -> Option<&str> {
+    self.values.get(key).map(String::as_str)
+  }
+
+  fn flag(&self, key: &str) -> bool {
+    self.values.contains_key(key)
+  }
+
+  fn require(&self, key: &str) -> Result<&str, String> {
+    self.get(key).filter(|v| !v.is_empty()).ok_or_else(|| format!("missing --{}", key))
+  }
+
+  fn number<T: std::str::FromStr>(&self, key: &str, default: Option<T>) -> Result<T, String> {
+    match (self.get(key), default) {
+      (None, Some(default)) => Ok(default),
+      (None, None) => Err(format!("missing --{}", key)),
+      (Some(value), _) => value.parse().map_err(|_| format!("--{} is not a number: `{}`", key, value)),
+    }
+  }
+
+  fn field(&self, key: &str, default: Option<u64>) -> Result<FieldElement, String> {
+    match (self.get(key), default) {
+      (None, Some(default)) => Ok(FieldElement(Fp::from(default))),
+      (None, None) => Err(format!("missing --{}", key)),
+      (Some(value), _) => parse_field(value).map(FieldElement).map_err(|e| format!("--{}: {}", key, e)),
+    }
+  }
+}
+
+/// Reads the circuit and its parameters from `--input`, or from the flags
+/// after the circuit name.
+fn parse_input(circuit: Option<&str>, options: &Options) -> Result<CircuitInput, String> {
+  if let Some(path) = options.get("input") {
+    return CircuitInput::from_path(path).map_err(|e| format!("{}: {}", path, e));
+  }
+
+  match circuit {
+    Some("fibonacci") =>
+      Ok(CircuitInput::Fibonacci(FibonacciInput {
+        n: options.number("n", None)?,
+        f0: options.field("f0", Some(1))?,
+        f1: options.field("f1", Some(1))?,
+        private: options.flag("private"),
+        out: options.get("out-value").map(|_| options.field("out-value", None)).transpose()?,
+      })),
+    Some("range") =>
+      Ok(CircuitInput::Range(RangeInput {
+        range: options.number("range", None)?,
+        value: options.get("value").map(|_| options.field("value", None)).transpose()?,
+      })),
+    Some(name) => Err(format!("unknown circuit `{}`", name)),
+    None => Err("expected a circuit or --input".to_string()),
+  }
+}
+
+/// Identifier recorded in proof envelopes. It covers every parameter the
+/// verifying key depends on.
+fn circuit_id(input: &CircuitInput) -> String {
+  match input {
+    CircuitInput::Fibonacci(input) if input.private => format!("fibonacci/private/{}", input.n),
+    CircuitInput::Fibonacci(input) => format!("fibonacci/public/{}", input.n),
+    CircuitInput::Range(input) => format!("range/{}", input.range),
+  }
+}
+
+fn run(args: &[String]) -> Result<ExitCode, String> {
+  let (command, rest) = args.split_first().ok_or("expected a command")?;
+  let command = Command::parse(command)?;
+  let (circuit, options) = match rest.split_first() {
+    Some((circuit, options)) if !circuit.starts_with("--") => (Some(circuit.as_str()), options),
+    _ => (None, rest),
+  };
+  let options = Options::parse(options)?;
+  let input = parse_input(circuit, &options)?;
+  let id = circuit_id(&input);
+
+  match &input {
+    CircuitInput::Fibonacci(input) => {
+      let circuit = input.circuit().map_err(|e| e.to_string())?;
+      let k = circuit.k();
+      execute(command, &id, &options, circuit, k, input.instances())
+    }
+    CircuitInput::Range(input) if input.value.is_none() && command.needs_witness() =>
+      Err("missing --value".to_string()),
+    CircuitInput::Range(input) =>
+      match input.range {
+        2 => execute_range::<2>(command, &id, &options, input),
+        4 => execute_range::<4>(command, &id, &options, input),
+        8 => execute_range::<8>(command, &id, &options, input),
+        16 => execute_range::<16>(command, &id, &options, input),
+        256 => execute_range::<256>(command, &id, &options, input),
+        65536 => execute_range::<65536>(command, &id, &options, input),
+        range => Err(format!("unsupported range {}", range)),
+      }
+  }
+}
+
+fn execute_range<const RANGE: usize>(
+  command: Command,
+  id: &str,
+  options: &Options,
+  input: &RangeInput
+) -> Result<ExitCode, String> {
+  let circuit = input.circuit::<RANGE>().map_err(|e| e.to_string())?;
+  let k = circuit.k();
+  execute(command, id, options, circuit, k, input.instances())
+}
+
+fn execute<C: Circuit<Fp> + std::fmt::Debug>(
+  command: Command,
+  id: &str,
+  options: &Options,
+  circuit: C,
+  k: u32,
+  instances: Vec<Vec<Fp>>
+) -> Result<ExitCode, String> {
+  let store = KeyStore::new(options.get("keys").unwrap_or("keys"));
+  let key_name = id.replace('/', "-");
+
+  match command {
+    Command::Mock => {
+      let prover = MockProver::run(k, &circuit, instances).map_err(|e| format!("{:?}", e))?;
+      match prover.verify() {
+        Ok(()) => {
+          println!("{}: constraints satisfied (k = {})", id, k);
+          Ok(ExitCode::SUCCESS)
+        }
+        Err(failures) => {
+          for failure in failures {
+            println!("{}", failure);
+          }
+          Ok(ExitCode::from(1))
+        }
+      }
+    }
+    Command::Keygen => {
+      let (_, pk) = store.setup(&key_name, k, &circuit).map_err(|e| e.to_string())?;
+      println!("circuit  {}", id);
+      println!("k        {}", k);
+      println!("vk hash  {}", to_hex(&vk_fingerprint(pk.get_vk())));
+      println!("params   {}", store.params_path(k).display());
+      println!("vk       {}", store.vk_path(&key_name, k).display());
+      Ok(ExitCode::SUCCESS)
+    }
+    Command::Prove => {
+      let out = PathBuf::from(options.require("out")?);
+      let (params, pk) = store.setup(&key_name, k, &circuit).map_err(|e| e.to_string())?;
+      let proof = prove(&params, &pk, circuit, &instances).map_err(|e| format!("{:?}", e))?;
+      let envelope = ProofEnvelope::new(id, k, pk.get_vk(), instances, proof);
+
+      let bytes = if out.extension().is_some_and(|ext| ext == "json") {
+        envelope.to_json().map_err(|e| e.to_string())?.into_bytes()
+      } else {
+        envelope.to_bytes()
+      };
+      fs::write(&out, bytes).map_err(|e| format!("{}: {}", out.display(), e))?;
+      println!("wrote {}", out.display());
+      Ok(ExitCode::SUCCESS)
+    }
+    Command::Verify => {
+      let path = options.require("proof")?;
+      let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+      let envelope = if bytes.first() == Some(&b'{') {
+        let json = String::from_utf8(bytes).map_err(|_| format!("{}: not UTF-8", path))?;
+        ProofEnvelope::from_json(&json)
+      } else {
+        ProofEnvelope::from_bytes(&bytes)
+      };
+      let envelope = match envelope {
+        Ok(envelope) => envelope,
+        Err(err) => {
+          println!("{}: {}", path, err);
+          return Ok(ExitCode::from(1));
+        }
+      };
+
+      let (params, pk) = store.setup(&key_name, k, &circuit).map_err(|e| e.to_string())?;
+      match envelope.verify(id, &params, pk.get_vk()) {
+        Ok(()) => {
+          println!("{}: valid proof for {}", path, id);
+          for (column, values) in envelope.instances.iter().enumerate() {
+            for (row, value) in values.iter().enumerate() {
+              println!("  instance[{}][{}] = {:?}", column, row, value);
+            }
+          }
+          Ok(ExitCode::SUCCESS)
+        }
+        Err(err) => {
+          println!("{}: {}", path, err);
+          Ok(ExitCode::from(1))
+        }
+      }
+    }
+    Command::Layout => layout(id, options, &circuit, k),
+    Command::Stats =>
+      match CircuitStats::measure(&circuit, &instances) {
+        Ok(stats) => {
+          println!("{}", stats.to_json());
+          Ok(ExitCode::SUCCESS)
+        }
+        Err(err @ StatsError::Unsatisfied { .. }) => {
+          println!("{}: {}", id, err);
+          Ok(ExitCode::from(1))
+        }
+        Err(err) => Err(err.to_string()),
+      }
+  }
+}
+
+#[cfg(feature = "dev-graph")]
+fn layout<C: Circuit<Fp>>(id: &str, options: &Options, circuit: &C, k: u32) -> Result<ExitCode, String> {
+  use plotters::prelude::*;
+
+  let out = options.require("out")?;
+  let root = BitMapBackend::new(out, (1024, 3096)).into_drawing_area();
+  root.fill(&WHITE).map_err(|e| e.to_string())?;
+  let root = root.titled(id, ("sans-serif", 60)).map_err(|e| e.to_string())?;
+  halo2_proofs::dev::CircuitLayout::default()
+    .render(k, circuit, &root)
+    .map_err(|e| e.to_string())?;
+  println!("wrote {}", out);
+  Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(not(feature = "dev-graph"))]
+fn layout<C: Circuit<Fp>>(_: &str, _: &Options, _: &C, _: u32) -> Result<ExitCode, String> {
+  Err("layout rendering needs the dev-graph feature".to_string())
+}
+
+fn main() -> ExitCode {
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  if args.is_empty() || args[0] == "--help" || args[0] == "help" {
+    print!("{}", USAGE);
+    return ExitCode::SUCCESS;
+  }
+
+  match run(&args) {
+    Ok(code) => code,
+    Err(err) => {
+      eprintln!("error: {}\n\n{}", err, USAGE);
+      ExitCode::from(2)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+  }
+
+  #[test]
+  fn cli_parses_inputs() {
+    let options = Options::parse(&args("--n 9 --private --f0 -2 --f1 0x10")).unwrap();
+    let input = parse_input(Some("fibonacci"), &options).unwrap();
+    let CircuitInput::Fibonacci(fibonacci) = &input else { panic!("expected fibonacci") };
+    assert_eq!(fibonacci.f0, FieldElement(-Fp::from(2)));
+    assert_eq!(fibonacci.f1, FieldElement(Fp::from(16)));
+    assert_eq!(circuit_id(&input), "fibonacci/private/9");
+
+    let options = Options::parse(&args("--range 8 --value 7")).unwrap();
+    assert_eq!(circuit_id(&parse_input(Some("range"), &options).unwrap()), "range/8");
+
+    assert!(Options::parse(&args("9")).is_err());
+    assert!(parse_input(Some("fibonacci"), &Options::parse(&args("--n x")).unwrap()).is_err());
+    assert!(parse_input(Some("range"), &Options::parse(&args("--range 8 --value 1.5")).unwrap()).is_err());
+    assert!(parse_input(Some("sudoku"), &Options::default()).is_err());
+    assert!(parse_input(None, &Options::default()).is_err());
+  }
+
+  #[test]
+  fn cli_exit_codes() {
+    let dir = std::env::temp_dir().join(format!("halo2-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let keys = dir.join("keys");
+    let proof = dir.join("proof.json");
+    let common = format!("--n 9 --keys {}", keys.display());
+
+    let run_line = |line: String| run(&args(&line));
+
+    assert_eq!(run_line(format!("mock fibonacci {}", common)), Ok(ExitCode::SUCCESS));
+    assert_eq!(run_line("mock range --range 8 --value 8".to_string()), Ok(ExitCode::from(1)));
+    assert_eq!(run_line("stats range --range 256 --value 3".to_string()), Ok(ExitCode::SUCCESS));
+    assert_eq!(run_line("stats range --range 8 --value 8".to_string()), Ok(ExitCode::from(1)));
+
+    let prove_line = format!("prove fibonacci {} --out {}", common, proof.display());
+    assert_eq!(run_line(prove_line), Ok(ExitCode::SUCCESS));
+    let verify_line = format!("verify fibonacci {} --proof {}", common, proof.display());
+    assert_eq!(run_line(verify_line.clone()), Ok(ExitCode::SUCCESS));
+
+    // Another length is another circuit.
+    let other = format!("verify fibonacci --n 10 --keys {} --proof {}", keys.display(), proof.display());
+    assert_eq!(run_line(other), Ok(ExitCode::from(1)));
+
+    // Tampered public output.
+    let json = fs::read_to_string(&proof).unwrap();
+    let tampered = json.replace(&format!("{:?}", Fp::from(55)), &format!("{:?}", Fp::from(56)));
+    assert_ne!(json, tampered);
+    fs::write(&proof, tampered).unwrap();
+    assert_eq!(run_line(verify_line), Ok(ExitCode::from(1)));
+
+    // The same statement from an input file.
+    let input = dir.join("range.toml");
+    fs::write(&input, "circuit = \"range\"\nrange = 256\nvalue = \"0xff\"\n").unwrap();
+    assert_eq!(run_line(format!("mock --input {}", input.display())), Ok(ExitCode::SUCCESS));
+    fs::write(&input, "circuit = \"range\"\nrange = 256\nvalue = -1\n").unwrap();
+    assert_eq!(run_line(format!("mock --input {}", input.display())), Ok(ExitCode::from(1)));
+
+    assert!(run_line("prove fibonacci --n 9".to_string()).is_err());
+    assert!(run_line("mock fibonacci --n 1".to_string()).is_err());
+    assert!(run_line("mock range --range 7 --value 1".to_string()).is_err());
+    assert!(run_line("prove range --range 8".to_string()).is_err());
+
+    // The verifier of a range proof never sees the value.
+    let range_proof = dir.join("range.proof");
+    let range_keys = format!("--range 8 --keys {}", keys.display());
+    let prove_range = format!("prove range {} --value 5 --out {}", range_keys, range_proof.display());
+    assert_eq!(run_line(prove_range), Ok(ExitCode::SUCCESS));
+    assert_eq!(run_line(format!("keygen range {}", range_keys)), Ok(ExitCode::SUCCESS));
+    let verify_range = format!("verify range {} --proof {}", range_keys, range_proof.display());
+    assert_eq!(run_line(verify_range), Ok(ExitCode::SUCCESS));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
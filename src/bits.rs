@@ -0,0 +1,431 @@
+use halo2_proofs::{
+  plonk::{ Advice, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Selector },
+  circuit::*,
+  poly::Rotation,
+};
+
+use group::ff::PrimeField;
+
+use std::marker::PhantomData;
+
+/// Decomposes a value into little-endian bits `b_0, …, b_{n−1}`, one per
+/// row under a row holding the constants `acc = 0` and `le = 1`. Row `i + 1`
+/// enforces
+///
+///   `b_i · (1 − b_i) = 0` and `acc_{i+1} = acc_i + b_i · 2^i`,
+///
+/// with the weights `2^i` in a fixed column, so the last `acc` is the
+/// recomposed value. A one-bit decomposition is the boolean check that
+/// `RangeChip<F, 2>` makes, and the bits come back as cells.
+///
+/// Short decompositions stop at `F::CAPACITY` bits, where the sum cannot
+/// wrap. A full `F::NUM_BITS` decomposition can: for Pasta, `p < 2^255`,
+/// so a small `x` also has the bits of `x + p`. Canonical decompositions
+/// reject those by comparing the bits with `p − 1`, from the lowest up,
+/// next to the running sum:
+///
+///   `le_{i+1} = m_i · (1 − b_i) + (1 − m_i − b_i + 2 · m_i · b_i) · le_i`,
+///
+/// where `m_i` are the bits of `p − 1` in another fixed column. `le` stays
+/// 1 while the bits so far are at most those of `p − 1`, and the last one
+/// is constrained to 1.
+#[derive(Clone, Debug)]
+pub struct BitsConfig {
+  bit: Column<Advice>,
+  acc: Column<Advice>,
+  le: Column<Advice>,
+  weight: Column<Fixed>,
+  modulus_bit: Column<Fixed>,
+  q_bit: Selector,
+  q_canonical: Selector,
+}
+
+/// A decomposed value and its bits, lowest first.
+#[derive(Clone, Debug)]
+pub struct AssignedBits<F: PrimeField> {
+  pub value: AssignedCell<F, F>,
+  pub bits: Vec<AssignedCell<F, F>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BitsChip<F: PrimeField> {
+  config: BitsConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> BitsChip<F> {
+  pub fn construct(config: BitsConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>) -> BitsConfig {
+    let [bit, acc, le] = [(); 3].map(|_| meta.advice_column());
+    let weight = meta.fixed_column();
+    let modulus_bit = meta.fixed_column();
+    let q_bit = meta.selector();
+    let q_canonical = meta.selector();
+
+    for column in [bit, acc, le] {
+      meta.enable_equality(column);
+    }
+
+    let one = Expression::Constant(F::ONE);
+
+    meta.create_gate("bit", |meta| {
+      let q = meta.query_selector(q_bit);
+      let bit = meta.query_advice(bit, Rotation::cur());
+      let acc_prev = meta.query_advice(acc, Rotation::prev());
+      let acc = meta.query_advice(acc, Rotation::cur());
+      let weight = meta.query_fixed(weight);
+
+      Constraints::with_selector(q, [
+        ("bit is boolean", bit.clone() * (one.clone() - bit.clone())),
+        ("acc = acc_prev + bit * weight", acc - (acc_prev + bit * weight)),
+      ])
+    });
+
+    meta.create_gate("canonical", |meta| {
+      let q = meta.query_selector(q_canonical);
+      let bit = meta.query_advice(bit, Rotation::cur());
+      let le_prev = meta.query_advice(le, Rotation::prev());
+      let le = meta.query_advice(le, Rotation::cur());
+      let m = meta.query_fixed(modulus_bit);
+      let same = one.clone() - m.clone() - bit.clone() + Expression::Constant(F::from(2)) * m.clone() * bit.clone();
+
+      Constraints::with_selector(q, [("le = m * (1 - bit) + same * le_prev", le - (m * (one.clone() - bit) + same * le_prev))])
+    });
+
+    // The first row of every decomposition and the final `le` are constants.
+    let constant = meta.fixed_column();
+    meta.enable_constant(constant);
+
+    BitsConfig { bit, acc, le, weight, modulus_bit, q_bit, q_canonical }
+  }
+
+  /// Witnesses `value` and checks it is 0 or 1.
+  pub fn assign_bool(&self, layouter: impl Layouter<F>, value: Value<F>) -> Result<AssignedCell<F, F>, Error> {
+    Ok(self.decompose(layouter, value, 1)?.value)
+  }
+
+  /// Checks a cell assigned by another gadget is 0 or 1.
+  pub fn check_bool(&self, layouter: impl Layouter<F>, cell: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+    Ok(self.decompose_cell(layouter, cell, 1)?.value)
+  }
+
+  /// Witnesses `value` and its lowest `num_bits` bits, and checks it fits
+  /// in them.
+  pub fn decompose(
+    &self,
+    layouter: impl Layouter<F>,
+    value: Value<F>,
+    num_bits: usize
+  ) -> Result<AssignedBits<F>, Error> {
+    Self::check_short(num_bits);
+    self.layout(layouter, None, value, lower_bits(value, num_bits), false)
+  }
+
+  /// Like [`Self::decompose`] for a cell assigned by another gadget, which
+  /// is copied in under an equality constraint.
+  pub fn decompose_cell(
+    &self,
+    layouter: impl Layouter<F>,
+    cell: &AssignedCell<F, F>,
+    num_bits: usize
+  ) -> Result<AssignedBits<F>, Error> {
+    Self::check_short(num_bits);
+    let value = cell.value().copied();
+    self.layout(layouter, Some(cell), value, lower_bits(value, num_bits), false)
+  }
+
+  /// Witnesses `value` and its `F::NUM_BITS` bits, checking they are the
+  /// canonical ones: below the modulus.
+  pub fn decompose_canonical(
+    &self,
+    layouter: impl Layouter<F>,
+    value: Value<F>
+  ) -> Result<AssignedBits<F>, Error> {
+    self.layout(layouter, None, value, lower_bits(value, F::NUM_BITS as usize), true)
+  }
+
+  /// Like [`Self::decompose_canonical`] for a cell assigned by another
+  /// gadget.
+  pub fn decompose_canonical_cell(
+    &self,
+    layouter: impl Layouter<F>,
+    cell: &AssignedCell<F, F>
+  ) -> Result<AssignedBits<F>, Error> {
+    let value = cell.value().copied();
+    self.layout(layouter, Some(cell), value, lower_bits(value, F::NUM_BITS as usize), true)
+  }
+
+  fn check_short(num_bits: usize) {
+    assert!(num_bits > 0, "a decomposition needs at least one bit");
+    assert!(num_bits <= F::CAPACITY as usize, "decompositions past F::CAPACITY bits must be canonical");
+  }
+
+  /// Lays out the decomposition of `value` into `bits`. The last running
+  /// sum is witnessed as `value` itself, so bits that do not recompose to
+  /// it fail the gate, and is tied to `input` when there is one.
+  fn layout(
+    &self,
+    mut layouter: impl Layouter<F>,
+    input: Option<&AssignedCell<F, F>>,
+    value: Value<F>,
+    bits: Vec<Value<F>>,
+    canonical: bool
+  ) -> Result<AssignedBits<F>, Error> {
+    let config = &self.config;
+    let max = -F::ONE;
+
+    layouter.assign_region(
+      || if canonical { "canonical bits" } else { "bits" },
+      |mut region| {
+        let mut acc = region.assign_advice_from_constant(|| "acc", config.acc, 0, F::ZERO)?;
+        let mut le = if canonical {
+          Some(region.assign_advice_from_constant(|| "le", config.le, 0, F::ONE)?)
+        } else {
+          None
+        };
+
+        let mut weight = F::ONE;
+        let mut cells = Vec::with_capacity(bits.len());
+        for (i, &bit) in bits.iter().enumerate() {
+          let row = i + 1;
+          config.q_bit.enable(&mut region, row)?;
+          region.assign_fixed(|| "weight", config.weight, row, || Value::known(weight))?;
+          let bit = region.assign_advice(|| "bit", config.bit, row, || bit)?;
+
+          let sum = if row == bits.len() {
+            value
+          } else {
+            acc.value().zip(bit.value()).map(|(acc, bit)| *acc + *bit * weight)
+          };
+          acc = region.assign_advice(|| "acc", config.acc, row, || sum)?;
+
+          if let Some(prev) = le {
+            config.q_canonical.enable(&mut region, row)?;
+            let m = if repr_bit(&max, i) { F::ONE } else { F::ZERO };
+            region.assign_fixed(|| "modulus bit", config.modulus_bit, row, || Value::known(m))?;
+
+            let next = prev.value().zip(bit.value()).map(|(le, bit)| {
+              let same = F::ONE - m - bit + F::from(2) * m * bit;
+              m * (F::ONE - bit) + same * le
+            });
+            le = Some(region.assign_advice(|| "le", config.le, row, || next)?);
+          }
+
+          weight = weight.double();
+          cells.push(bit);
+        }
+
+        if let Some(le) = le {
+          region.constrain_constant(le.cell(), F::ONE)?;
+        }
+        if let Some(input) = input {
+          region.constrain_equal(input.cell(), acc.cell())?;
+        }
+
+        Ok(AssignedBits { value: acc, bits: cells })
+      }
+    )
+  }
+}
+
+/// Bit `i` of the little-endian representation of `value`.
+fn repr_bit<F: PrimeField>(value: &F, i: usize) -> bool {
+  (value.to_repr().as_ref()[i / 8] >> (i % 8)) & 1 == 1
+}
+
+/// The lowest `num_bits` bits of `value` as field elements.
+fn lower_bits<F: PrimeField>(value: Value<F>, num_bits: usize) -> Vec<Value<F>> {
+  (0..num_bits)
+    .map(|i| value.map(|v| if repr_bit(&v, i) { F::ONE } else { F::ZERO }))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use halo2_proofs::{
+    dev::MockProver,
+    pasta::{ Fp, Fq },
+    plonk::{ Circuit, Instance },
+  };
+  use rand_core::{ OsRng, RngCore };
+
+  /// Decomposes `value` and exposes it followed by its bits. With `forged`
+  /// set, those bits are laid out instead of the honest ones.
+  #[derive(Clone, Default)]
+  struct BitsCircuit<F: PrimeField> {
+    value: Value<F>,
+    /// `None` for a canonical decomposition.
+    num_bits: Option<usize>,
+    forged: Option<Vec<F>>,
+  }
+
+  impl<F: PrimeField> Circuit<F> for BitsCircuit<F> {
+    type Config = (BitsConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+      Self { value: Value::unknown(), num_bits: self.num_bits, forged: None }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+      let instance = meta.instance_column();
+      meta.enable_equality(instance);
+      (BitsChip::configure(meta), instance)
+    }
+
+    fn synthesize(&self, (config, instance): Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+      let chip = BitsChip::construct(config);
+      let namespace = layouter.namespace(|| "decompose");
+      let AssignedBits { value, bits } = match (&self.forged, self.num_bits) {
+        (Some(forged), num_bits) => {
+          let bits = forged.iter().map(|&bit| Value::known(bit)).collect();
+          chip.layout(namespace, None, self.value, bits, num_bits.is_none())?
+        }
+        (None, Some(num_bits)) => chip.decompose(namespace, self.value, num_bits)?,
+        (None, None) => chip.decompose_canonical(namespace, self.value)?,
+      };
+
+      for (row, cell) in [value].iter().chain(&bits).enumerate() {
+        layouter.constrain_instance(cell.cell(), instance, row)?;
+      }
+      Ok(())
+    }
+  }
+
+  /// `value` followed by `bits`, as the circuit exposes them.
+  fn instances<F: PrimeField>(value: F, bits: &[bool]) -> Vec<F> {
+    let bits = bits.iter().map(|&bit| if bit { F::ONE } else { F::ZERO });
+    std::iter::once(value).chain(bits).collect()
+  }
+
+  fn native<F: PrimeField>(value: F, num_bits: usize) -> Vec<bool> {
+    (0..num_bits).map(|i| repr_bit(&value, i)).collect()
+  }
+
+  fn verify<F: PrimeField + Ord>(circuit: &BitsCircuit<F>, instances: Vec<F>) -> bool {
+    let k = crate::stats::RowUsage::measure(circuit, &[instances.len()]).unwrap().k();
+    MockProver::run(k, circuit, vec![instances]).unwrap().verify().is_ok()
+  }
+
+  #[test]
+  fn bits_match_native() {
+    for num_bits in [1, 8, 13, 64] {
+      let mask = if num_bits == 64 { u64::MAX } else { (1 << num_bits) - 1 };
+      for value in [0, 1, mask, OsRng.next_u64() & mask] {
+        let value = Fp::from(value);
+        let bits = native(value, num_bits);
+        let circuit = BitsCircuit { value: Value::known(value), num_bits: Some(num_bits), forged: None };
+        assert!(verify(&circuit, instances(value, &bits)), "{:?} in {} bits", value, num_bits);
+
+        // Flipping any bit is caught.
+        for i in [0, num_bits - 1] {
+          let mut wrong = bits.clone();
+          wrong[i] = !wrong[i];
+          assert!(!verify(&circuit, instances(value, &wrong)));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn bits_reject_wide_values() {
+    // 256 does not fit in 8 bits, and 2 is not a bit.
+    for (value, num_bits) in [(256, 8), (2, 1)] {
+      let value = Fp::from(value);
+      let circuit = BitsCircuit { value: Value::known(value), num_bits: Some(num_bits), forged: None };
+      assert!(!verify(&circuit, instances(value, &native(value, num_bits))));
+    }
+
+    // Bits of 3 still recompose 2 · 3 = 6, but fail the boolean check.
+    let value = Fp::from(6);
+    let circuit = BitsCircuit {
+      value: Value::known(value),
+      num_bits: Some(2),
+      forged: Some(vec![Fp::zero(), Fp::from(3)]),
+    };
+    let mut expected = instances(value, &[false, false]);
+    expected[2] = Fp::from(3);
+    assert!(!verify(&circuit, expected));
+  }
+
+  fn check_canonical<F: PrimeField + Ord>() {
+    let num_bits = F::NUM_BITS as usize;
+    let random = F::from(OsRng.next_u64()) * F::from(OsRng.next_u64()) * F::from(OsRng.next_u64());
+
+    for value in [F::ZERO, F::ONE, -F::ONE, random] {
+      let circuit = BitsCircuit { value: Value::known(value), num_bits: None, forged: None };
+      assert!(verify(&circuit, instances(value, &native(value, num_bits))));
+    }
+
+    // `x + p` still fits in `NUM_BITS` bits for a small `x`: add `x + 1`
+    // to the bits of `p - 1`.
+    let x = 5;
+    let mut aliased = native(-F::ONE, num_bits);
+    let mut carry = x + 1;
+    for bit in aliased.iter_mut() {
+      let sum = *bit as u64 + carry;
+      *bit = sum & 1 == 1;
+      carry = sum >> 1;
+    }
+    assert_eq!(carry, 0);
+    assert_ne!(aliased, native(F::from(x), num_bits));
+
+    // The recomposition alone accepts them, the canonical check does not.
+    let value = F::from(x);
+    let forged = aliased.iter().map(|&bit| if bit { F::ONE } else { F::ZERO }).collect::<Vec<_>>();
+    for (num_bits, accepted) in [(Some(num_bits), true), (None, false)] {
+      let circuit = BitsCircuit { value: Value::known(value), num_bits, forged: Some(forged.clone()) };
+      assert_eq!(verify(&circuit, instances(value, &aliased)), accepted);
+    }
+  }
+
+  #[test]
+  fn bits_canonical_pasta() {
+    check_canonical::<Fp>();
+    check_canonical::<Fq>();
+  }
+
+  /// Checks `value` is boolean by copying it into the chip, and exposes it.
+  #[derive(Default)]
+  struct BoolCircuit {
+    value: Value<Fp>,
+  }
+
+  impl Circuit<Fp> for BoolCircuit {
+    type Config = (BitsConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+      Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+      let instance = meta.instance_column();
+      meta.enable_equality(instance);
+      (BitsChip::configure(meta), instance)
+    }
+
+    fn synthesize(&self, (config, instance): Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+      let chip = BitsChip::construct(config);
+      let value = chip.assign_bool(layouter.namespace(|| "bool"), self.value)?;
+      let copy = chip.check_bool(layouter.namespace(|| "copy"), &value)?;
+      layouter.constrain_instance(copy.cell(), instance, 0)
+    }
+  }
+
+  #[test]
+  fn bool_matches_range_two() {
+    for value in [0, 1, 2, 5] {
+      let value = Fp::from(value);
+      let prover = MockProver::run(4, &BoolCircuit { value: Value::known(value) }, vec![vec![value]]).unwrap();
+      assert_eq!(prover.verify().is_ok(), crate::range_check::range_check_native(value, 2));
+    }
+  }
+}
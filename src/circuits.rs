@@ -0,0 +1,44 @@
+//! Complete circuits over the [`crate::gadgets`], with the instances they
+//! expect and native reference implementations to test them against.
+//!
+//! ```
+//! use halo2::circuits::FiboCircuit;
+//! use halo2_proofs::{ dev::MockProver, pasta::Fp };
+//!
+//! let circuit = FiboCircuit::<Fp>::new(9);
+//! let instances = circuit.instances(Fp::from(1), Fp::from(1));
+//! assert_eq!(instances, vec![vec![Fp::from(1), Fp::from(1), Fp::from(55), Fp::from(9)]]);
+//!
+//! let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+//! assert_eq!(prover.verify(), Ok(()));
+//! ```
+
+pub use crate::{
+  fibonacci::{
+    fibonacci_instances,
+    fibonacci_native,
+    FiboCircuit,
+    SeedMode,
+    F0_ROW,
+    F1_ROW,
+    INDEX_ROW,
+    OUT_ROW,
+  },
+  fibonacci_bounded::{ BoundedFiboCircuit, BoundedFibonacciConfig },
+  fibonacci_fast::FastFiboCircuit,
+  fibonacci_rotation::FiboRotationCircuit,
+  range_check::{ field_to_u128, range_check_native, RangeCircuit },
+  range_check_decompose::{ lower_bits, DecomposeCircuit },
+  range_check_dynamic::{ dynamic_range_native, DynamicRangeCircuit },
+  range_check_interval::{ interval_check_native, DecomposeIntervalCircuit, IntervalCircuit },
+  range_check_lookup::RangeLookupCircuit,
+  recurrence::{
+    recurrence_instances,
+    recurrence_native,
+    Fibonacci,
+    Pell,
+    Recurrence,
+    RecurrenceCircuit,
+    Tribonacci,
+  },
+};
@@ -0,0 +1,413 @@
+use std::{ fmt, io::{ self, Read, Write } };
+
+use group::ff::PrimeField;
+use halo2_proofs::{
+  pasta::{ EqAffine, Fp },
+  plonk::VerifyingKey,
+  poly::commitment::Params,
+};
+use serde::{ Deserialize, Serialize };
+
+use crate::{ keys::{ from_hex, to_hex, vk_fingerprint, Fingerprint }, prover };
+
+/// Version written by this crate. Readers reject any other version.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Leading bytes of the binary encoding.
+pub const MAGIC: &[u8; 4] = b"H2PF";
+
+/// A proof together with what is needed to check it: which circuit it is
+/// for, the size of its domain, a fingerprint of the verifying key it was
+/// made with and the public inputs.
+///
+/// In JSON the fingerprint and proof are hex strings and field elements are
+/// `0x`-prefixed big-endian hex, as `Fp`'s `Debug` prints them. The binary
+/// encoding is `MAGIC`, then little-endian `u32`s for the version, lengths
+/// and `k`, and field elements as their 32-byte representation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofEnvelope {
+  pub version: u32,
+  pub circuit_id: String,
+  pub k: u32,
+  #[serde(with = "hex_bytes")]
+  pub vk_hash: Fingerprint,
+  #[serde(with = "hex_instances")]
+  pub instances: Vec<Vec<Fp>>,
+  #[serde(with = "hex_bytes")]
+  pub proof: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum EnvelopeError {
+  Io(io::Error),
+  Json(serde_json::Error),
+  /// The input is not a proof envelope.
+  Malformed(String),
+  UnsupportedVersion(u32),
+  CircuitMismatch { expected: String, found: String },
+  /// The envelope was made for a domain of another size.
+  SizeMismatch { expected: u32, found: u32 },
+  /// The proof was made with another verifying key.
+  KeyMismatch,
+  /// The envelope matches but the proof does not verify.
+  InvalidProof,
+}
+
+impl fmt::Display for EnvelopeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      EnvelopeError::Io(err) => write!(f, "I/O error: {}", err),
+      EnvelopeError::Json(err) => write!(f, "invalid JSON envelope: {}", err),
+      EnvelopeError::Malformed(reason) => write!(f, "malformed proof envelope: {}", reason),
+      EnvelopeError::UnsupportedVersion(version) =>
+        write!(f, "unsupported envelope version {} (expected {})", version, FORMAT_VERSION),
+      EnvelopeError::CircuitMismatch { expected, found } =>
+        write!(f, "proof is for circuit {}, expected {}", found, expected),
+      EnvelopeError::SizeMismatch { expected, found } =>
+        write!(f, "proof is for k = {}, expected {}", found, expected),
+      EnvelopeError::KeyMismatch => write!(f, "proof was made with a different verifying key"),
+      EnvelopeError::InvalidProof => write!(f, "proof does not verify"),
+    }
+  }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+impl From<io::Error> for EnvelopeError {
+  fn from(err: io::Error) -> Self {
+    EnvelopeError::Io(err)
+  }
+}
+
+impl From<serde_json::Error> for EnvelopeError {
+  fn from(err: serde_json::Error) -> Self {
+    EnvelopeError::Json(err)
+  }
+}
+
+impl ProofEnvelope {
+  pub fn new(
+    circuit_id: impl Into<String>,
+    k: u32,
+    vk: &VerifyingKey<EqAffine>,
+    instances: Vec<Vec<Fp>>,
+    proof: Vec<u8>
+  ) -> Self {
+    Self {
+      version: FORMAT_VERSION,
+      circuit_id: circuit_id.into(),
+      k,
+      vk_hash: vk_fingerprint(vk),
+      instances,
+      proof,
+    }
+  }
+
+  /// Checks the envelope is for `circuit_id`, `params` and `vk`, then
+  /// verifies the proof against the public inputs it carries.
+  pub fn verify(
+    &self,
+    circuit_id: &str,
+    params: &Params<EqAffine>,
+    vk: &VerifyingKey<EqAffine>
+  ) -> Result<(), EnvelopeError> {
+    if self.version != FORMAT_VERSION {
+      return Err(EnvelopeError::UnsupportedVersion(self.version));
+    }
+    if self.circuit_id != circuit_id {
+      return Err(EnvelopeError::CircuitMismatch {
+        expected: circuit_id.to_string(),
+        found: self.circuit_id.clone(),
+      });
+    }
+    let k = params.get_g().len().trailing_zeros();
+    if self.k != k {
+      return Err(EnvelopeError::SizeMismatch { expected: k, found: self.k });
+    }
+    if self.vk_hash != vk_fingerprint(vk) {
+      return Err(EnvelopeError::KeyMismatch);
+    }
+    if !prover::verify(params, vk, &self.proof, &self.instances) {
+      return Err(EnvelopeError::InvalidProof);
+    }
+    Ok(())
+  }
+
+  pub fn to_json(&self) -> Result<String, EnvelopeError> {
+    Ok(serde_json::to_string_pretty(self)?)
+  }
+
+  pub fn from_json(json: &str) -> Result<Self, EnvelopeError> {
+    let envelope: Self = serde_json::from_str(json)?;
+    if envelope.version != FORMAT_VERSION {
+      return Err(EnvelopeError::UnsupportedVersion(envelope.version));
+    }
+    Ok(envelope)
+  }
+
+  pub fn write_binary<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    write_u32(writer, self.version)?;
+    write_bytes(writer, self.circuit_id.as_bytes())?;
+    write_u32(writer, self.k)?;
+    writer.write_all(&self.vk_hash)?;
+
+    write_u32(writer, self.instances.len() as u32)?;
+    for column in &self.instances {
+      write_u32(writer, column.len() as u32)?;
+      for value in column {
+        writer.write_all(value.to_repr().as_ref())?;
+      }
+    }
+
+    write_bytes(writer, &self.proof)
+  }
+
+  pub fn read_binary<R: Read>(reader: &mut R) -> Result<Self, EnvelopeError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+      return Err(EnvelopeError::Malformed("bad magic bytes".to_string()));
+    }
+    let version = read_u32(reader)?;
+    if version != FORMAT_VERSION {
+      return Err(EnvelopeError::UnsupportedVersion(version));
+    }
+
+    let circuit_id = String::from_utf8(read_bytes(reader)?)
+      .map_err(|_| EnvelopeError::Malformed("circuit id is not UTF-8".to_string()))?;
+    let k = read_u32(reader)?;
+    let mut vk_hash = [0u8; 32];
+    reader.read_exact(&mut vk_hash)?;
+
+    let columns = read_u32(reader)?;
+    let mut instances = vec![];
+    for _ in 0..columns {
+      let rows = read_u32(reader)?;
+      let mut column = vec![];
+      for _ in 0..rows {
+        let mut repr = <Fp as PrimeField>::Repr::default();
+        reader.read_exact(repr.as_mut())?;
+        let value = Option::from(Fp::from_repr(repr))
+          .ok_or_else(|| EnvelopeError::Malformed("non-canonical field element".to_string()))?;
+        column.push(value);
+      }
+      instances.push(column);
+    }
+
+    let proof = read_bytes(reader)?;
+
+    Ok(Self { version, circuit_id, k, vk_hash, instances, proof })
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = vec![];
+    self.write_binary(&mut bytes).expect("writing to a Vec cannot fail");
+    bytes
+  }
+
+  pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, EnvelopeError> {
+    let envelope = Self::read_binary(&mut bytes)?;
+    if !bytes.is_empty() {
+      return Err(EnvelopeError::Malformed("trailing bytes".to_string()));
+    }
+    Ok(envelope)
+  }
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+  writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+  let mut bytes = [0u8; 4];
+  reader.read_exact(&mut bytes)?;
+  Ok(u32::from_le_bytes(bytes))
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+  write_u32(writer, bytes.len() as u32)?;
+  writer.write_all(bytes)
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+  let len = read_u32(reader)? as usize;
+  let mut bytes = vec![];
+  reader.take(len as u64).read_to_end(&mut bytes)?;
+  if bytes.len() != len {
+    return Err(io::ErrorKind::UnexpectedEof.into());
+  }
+  Ok(bytes)
+}
+
+/// `0x`-prefixed big-endian hex, the format `Fp`'s `Debug` uses.
+pub fn fp_to_hex(value: &Fp) -> String {
+  format!("{:?}", value)
+}
+
+/// Inverse of [`fp_to_hex`]. Shorter inputs are zero-extended.
+pub fn fp_from_hex(hex: &str) -> Option<Fp> {
+  let digits = hex.strip_prefix("0x")?;
+  if digits.is_empty() || digits.len() > 64 {
+    return None;
+  }
+  let mut be = from_hex(&format!("{:0>64}", digits))?;
+  be.reverse();
+  let mut repr = <Fp as PrimeField>::Repr::default();
+  repr.as_mut().copy_from_slice(&be);
+  Option::from(Fp::from_repr(repr))
+}
+
+mod hex_bytes {
+  use serde::{ de::Error, Deserialize, Deserializer, Serializer };
+
+  pub fn serialize<S: Serializer, T: AsRef<[u8]>>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&super::to_hex(bytes.as_ref()))
+  }
+
+  pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where D: Deserializer<'de>, T: TryFrom<Vec<u8>>
+  {
+    let hex = String::deserialize(deserializer)?;
+    let bytes = super::from_hex(&hex).ok_or_else(|| D::Error::custom("invalid hex"))?;
+    T::try_from(bytes).map_err(|_| D::Error::custom("wrong byte length"))
+  }
+}
+
+mod hex_instances {
+  use halo2_proofs::pasta::Fp;
+  use serde::{ de::Error, Deserialize, Deserializer, Serialize, Serializer };
+
+  pub fn serialize<S: Serializer>(instances: &[Vec<Fp>], serializer: S) -> Result<S::Ok, S::Error> {
+    let hex: Vec<Vec<String>> = instances
+      .iter()
+      .map(|column| column.iter().map(super::fp_to_hex).collect())
+      .collect();
+    hex.serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<Fp>>, D::Error> {
+    let hex = Vec::<Vec<String>>::deserialize(deserializer)?;
+    hex
+      .iter()
+      .map(|column| {
+        column
+          .iter()
+          .map(|value| {
+            super::fp_from_hex(value).ok_or_else(|| D::Error::custom("invalid field element"))
+          })
+          .collect()
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fibonacci::FiboCircuit,
+    prover::{ prove, setup },
+  };
+
+  const FIBONACCI: &str = "fibonacci/v1";
+
+  fn envelope() -> (ProofEnvelope, Params<EqAffine>, VerifyingKey<EqAffine>) {
+    let circuit = FiboCircuit::<Fp>::new(9);
+    let k = circuit.k();
+    let (params, pk) = setup(k, &circuit).unwrap();
+    let instances = circuit.instances(Fp::one(), Fp::one());
+    let proof = prove(&params, &pk, circuit, &instances).unwrap();
+    let envelope = ProofEnvelope::new(FIBONACCI, k, pk.get_vk(), instances, proof);
+    (envelope, params, pk.get_vk().clone())
+  }
+
+  #[test]
+  fn envelope_round_trip() {
+    let (envelope, params, vk) = envelope();
+    envelope.verify(FIBONACCI, &params, &vk).unwrap();
+
+    let json = envelope.to_json().unwrap();
+    assert!(json.contains("\"circuit_id\": \"fibonacci/v1\""));
+    assert!(json.contains(&format!("{:?}", Fp::from(55))));
+    assert_eq!(ProofEnvelope::from_json(&json).unwrap(), envelope);
+
+    let bytes = envelope.to_bytes();
+    assert_eq!(&bytes[..4], MAGIC);
+    assert_eq!(ProofEnvelope::from_bytes(&bytes).unwrap(), envelope);
+  }
+
+  #[test]
+  fn envelope_rejects_mismatches() {
+    let (envelope, params, vk) = envelope();
+
+    assert!(matches!(
+      envelope.verify("range/8", &params, &vk),
+      Err(EnvelopeError::CircuitMismatch { .. })
+    ));
+
+    // Same shape, but the length is fixed into the key.
+    let other = FiboCircuit::<Fp>::new(10);
+    let (_, other_pk) = setup(envelope.k, &other).unwrap();
+    assert!(matches!(
+      envelope.verify(FIBONACCI, &params, other_pk.get_vk()),
+      Err(EnvelopeError::KeyMismatch)
+    ));
+
+    let (bigger, _) = setup(envelope.k + 1, &FiboCircuit::<Fp>::new(9)).unwrap();
+    assert!(matches!(
+      envelope.verify(FIBONACCI, &bigger, &vk),
+      Err(EnvelopeError::SizeMismatch { .. })
+    ));
+
+    let mut tampered = envelope.clone();
+    tampered.instances[0][2] += Fp::one();
+    assert!(matches!(
+      tampered.verify(FIBONACCI, &params, &vk),
+      Err(EnvelopeError::InvalidProof)
+    ));
+
+    let mut future = envelope.clone();
+    future.version = FORMAT_VERSION + 1;
+    assert!(matches!(
+      future.verify(FIBONACCI, &params, &vk),
+      Err(EnvelopeError::UnsupportedVersion(_))
+    ));
+    assert!(matches!(
+      ProofEnvelope::from_bytes(&future.to_bytes()),
+      Err(EnvelopeError::UnsupportedVersion(_))
+    ));
+    assert!(matches!(
+      ProofEnvelope::from_json(&future.to_json().unwrap()),
+      Err(EnvelopeError::UnsupportedVersion(_))
+    ));
+  }
+
+  #[test]
+  fn envelope_rejects_malformed() {
+    let (envelope, _, _) = envelope();
+    let bytes = envelope.to_bytes();
+
+    assert!(matches!(ProofEnvelope::from_bytes(&bytes[1..]), Err(EnvelopeError::Malformed(_))));
+    assert!(matches!(
+      ProofEnvelope::from_bytes(&bytes[..bytes.len() - 1]),
+      Err(EnvelopeError::Io(_))
+    ));
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(ProofEnvelope::from_bytes(&trailing), Err(EnvelopeError::Malformed(_))));
+
+    let json = envelope.to_json().unwrap().replace("\"0x", "\"0xzz");
+    assert!(matches!(ProofEnvelope::from_json(&json), Err(EnvelopeError::Json(_))));
+  }
+
+  #[test]
+  fn fp_hex_round_trip() {
+    for value in [Fp::zero(), Fp::one(), Fp::from(55), -Fp::one()] {
+      assert_eq!(fp_from_hex(&fp_to_hex(&value)), Some(value));
+    }
+    assert_eq!(fp_from_hex("0x37"), Some(Fp::from(55)));
+    assert_eq!(fp_from_hex("37"), None);
+    assert_eq!(fp_from_hex(&format!("0x{}", "f".repeat(64))), None);
+  }
+}
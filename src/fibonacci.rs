@@ -1,22 +1,91 @@
-use std::marker::PhantomData;
-use halo2_proofs::{ arithmetic::Field, circuit::*, plonk::*, poly::Rotation };
+use std::{ fmt::Debug, marker::PhantomData };
+use halo2_proofs::{ circuit::*, plonk::*, poly::Rotation };
+
+use group::ff::PrimeField;
+
+use crate::stats::k_for;
+
+/// Instance column rows used by [`FiboCircuit`] with public seeds:
+/// `[f(0), f(1), f(n), n]`.
+pub const F0_ROW: usize = 0;
+pub const F1_ROW: usize = 1;
+pub const OUT_ROW: usize = 2;
+pub const INDEX_ROW: usize = 3;
+
+/// Where [`FiboCircuit`] takes `f(0)` and `f(1)` from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeedMode {
+  /// Seeds are read from the instance column: `[f(0), f(1), f(n), n]`.
+  Public,
+  /// Seeds are private witnesses; only `[f(n), n]` is public.
+  Private,
+}
+
+impl SeedMode {
+  pub fn out_row(self) -> usize {
+    match self {
+      SeedMode::Public => OUT_ROW,
+      SeedMode::Private => 0,
+    }
+  }
+
+  pub fn index_row(self) -> usize {
+    self.out_row() + 1
+  }
+}
+
+/// The instructions [`FiboCircuit`] needs from a Fibonacci layout, so the
+/// circuit can switch between chips without changing its synthesis code.
+pub trait FibonacciInstructions<F: PrimeField>: Chip<F> {
+  /// A cell holding a sequence element or the index `n`.
+  type Num: Clone + Debug;
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config;
+
+  fn construct(config: Self::Config) -> Self;
+
+  /// Rows the layout uses to reach `f(n)`, including the index.
+  fn rows(n: usize) -> usize;
+
+  /// Assigns the sequence from `f(0)` and `f(1)` up to `f(n)`. Seeds are read
+  /// from the instance column or witnessed from `f0`/`f1` depending on
+  /// `mode`. Returns the cells holding `f(n)` and `n`.
+  fn assign_sequence(
+    &self,
+    layouter: impl Layouter<F>,
+    mode: SeedMode,
+    f0: Value<F>,
+    f1: Value<F>,
+    n: usize
+  ) -> Result<(Self::Num, Self::Num), Error>;
+
+  fn expose_public(
+    &self,
+    layouter: impl Layouter<F>,
+    num: &Self::Num,
+    row: usize
+  ) -> Result<(), Error>;
+}
 
 #[derive(Clone, Copy, Debug)]
-struct FibonacciConfig {
-  pub col_a: Column<Advice>,
-  pub col_b: Column<Advice>,
-  pub col_c: Column<Advice>,
-  pub selector: Selector,
-  pub instance: Column<Instance>,
+pub struct FibonacciConfig {
+  col_a: Column<Advice>,
+  col_b: Column<Advice>,
+  col_c: Column<Advice>,
+  selector: Selector,
+  instance: Column<Instance>,
 }
 
+/// The three cells of the first row: `f(0)`, `f(1)` and `f(2)`.
+type FirstRow<F> = (AssignedCell<F, F>, AssignedCell<F, F>, AssignedCell<F, F>);
+
 #[derive(Debug, Clone)]
-struct FibonacciChip<F: Field> {
+pub struct FibonacciChip<F: PrimeField> {
   config: FibonacciConfig,
   _marker: PhantomData<F>,
 }
 
-impl<F: Field> FibonacciChip<F> {
+impl<F: PrimeField> FibonacciChip<F> {
   pub fn construct(config: FibonacciConfig) -> Self {
     Self {
       config,
@@ -25,16 +94,28 @@ impl<F: Field> FibonacciChip<F> {
   }
 
   pub fn configure(meta: &mut ConstraintSystem<F>) -> FibonacciConfig {
-    let col_a = meta.advice_column();
-    let col_b = meta.advice_column();
-    let col_c = meta.advice_column();
-    let selector = meta.selector();
+    let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
     let instance = meta.instance_column();
+    let constant = meta.fixed_column();
+    Self::configure_with_columns(meta, advice, instance, constant)
+  }
+
+  /// Like [`Self::configure`] on columns owned by the caller, so other chips
+  /// can share them. Equality is enabled on all of them and `constant` is
+  /// registered for constants.
+  pub fn configure_with_columns(
+    meta: &mut ConstraintSystem<F>,
+    [col_a, col_b, col_c]: [Column<Advice>; 3],
+    instance: Column<Instance>,
+    constant: Column<Fixed>
+  ) -> FibonacciConfig {
+    let selector = meta.selector();
 
     meta.enable_equality(col_a);
     meta.enable_equality(col_b);
     meta.enable_equality(col_c);
     meta.enable_equality(instance);
+    meta.enable_constant(constant);
 
     meta.create_gate("add", |meta| {
       let s = meta.query_selector(selector);
@@ -56,7 +137,7 @@ impl<F: Field> FibonacciChip<F> {
   pub fn assign_first_row(
     &self,
     mut layouter: impl Layouter<F>
-  ) -> Result<(AssignedCell<F, F>, AssignedCell<F, F>, AssignedCell<F, F>), Error> {
+  ) -> Result<FirstRow<F>, Error> {
     layouter.assign_region(
       || "first row",
       |mut region| {
@@ -65,7 +146,7 @@ impl<F: Field> FibonacciChip<F> {
         let a_cell = region.assign_advice_from_instance(
           || "f(0)",
           self.config.instance,
-          0,
+          F0_ROW,
           self.config.col_a,
           0
         )?;
@@ -73,7 +154,7 @@ impl<F: Field> FibonacciChip<F> {
         let b_cell = region.assign_advice_from_instance(
           || "f(1)",
           self.config.instance,
-          1,
+          F1_ROW,
           self.config.col_b,
           0
         )?;
@@ -90,6 +171,34 @@ impl<F: Field> FibonacciChip<F> {
     )
   }
 
+  /// Like [`Self::assign_first_row`], but witnesses `f(0)` and `f(1)`
+  /// privately instead of reading them from the instance column.
+  pub fn assign_first_row_private(
+    &self,
+    mut layouter: impl Layouter<F>,
+    f0: Value<F>,
+    f1: Value<F>
+  ) -> Result<FirstRow<F>, Error> {
+    layouter.assign_region(
+      || "first row",
+      |mut region| {
+        self.config.selector.enable(&mut region, 0)?;
+
+        let a_cell = region.assign_advice(|| "f(0)", self.config.col_a, 0, || f0)?;
+        let b_cell = region.assign_advice(|| "f(1)", self.config.col_b, 0, || f1)?;
+
+        let c_cell = region.assign_advice(
+          || "a + b",
+          self.config.col_c,
+          0,
+          || f0 + f1
+        )?;
+
+        Ok((a_cell, b_cell, c_cell))
+      }
+    )
+  }
+
   pub fn assign_row(
     &self,
     mut layouter: impl Layouter<F>,
@@ -117,6 +226,26 @@ impl<F: Field> FibonacciChip<F> {
     )
   }
 
+  /// Fixes the sequence index `n` as a constant so it can be exposed as a
+  /// public input next to `f(n)`.
+  pub fn assign_index(
+    &self,
+    mut layouter: impl Layouter<F>,
+    n: usize
+  ) -> Result<AssignedCell<F, F>, Error> {
+    layouter.assign_region(
+      || "index",
+      |mut region| {
+        region.assign_advice_from_constant(
+          || "n",
+          self.config.col_a,
+          0,
+          F::from(n as u64)
+        )
+      }
+    )
+  }
+
   pub fn expose_public(
     &self,
     mut layouter: impl Layouter<F>,
@@ -127,28 +256,51 @@ impl<F: Field> FibonacciChip<F> {
   }
 }
 
-#[derive(Default)]
-struct FiboCircuit<F>(PhantomData<F>);
-
-impl<F: Field> Circuit<F> for FiboCircuit<F> {
+impl<F: PrimeField> Chip<F> for FibonacciChip<F> {
   type Config = FibonacciConfig;
-  type FloorPlanner = SimpleFloorPlanner;
+  type Loaded = ();
 
-  fn without_witnesses(&self) -> Self {
-    Self::default()
+  fn config(&self) -> &Self::Config {
+    &self.config
+  }
+
+  fn loaded(&self) -> &Self::Loaded {
+    &()
   }
+}
+
+impl<F: PrimeField> FibonacciInstructions<F> for FibonacciChip<F> {
+  type Num = AssignedCell<F, F>;
 
   fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
     FibonacciChip::configure(meta)
   }
 
-  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
-    let chip = FibonacciChip::construct(config);
+  fn construct(config: Self::Config) -> Self {
+    FibonacciChip::construct(config)
+  }
 
-    let (_, mut prev_b, mut prev_c) = chip.assign_first_row(layouter.namespace(|| "first row"))?;
+  /// `n - 1` additions plus the index row.
+  fn rows(n: usize) -> usize {
+    n
+  }
 
-    for i in 3..10 {
-      let c_cell = chip.assign_row(
+  fn assign_sequence(
+    &self,
+    mut layouter: impl Layouter<F>,
+    mode: SeedMode,
+    f0: Value<F>,
+    f1: Value<F>,
+    n: usize
+  ) -> Result<(Self::Num, Self::Num), Error> {
+    let (_, mut prev_b, mut prev_c) = match mode {
+      SeedMode::Public => self.assign_first_row(layouter.namespace(|| "first row"))?,
+      SeedMode::Private =>
+        self.assign_first_row_private(layouter.namespace(|| "first row"), f0, f1)?,
+    };
+
+    for i in 3..=n {
+      let c_cell = self.assign_row(
         layouter.namespace(|| "next row"),
         &prev_b,
         &prev_c,
@@ -158,21 +310,139 @@ impl<F: Field> Circuit<F> for FiboCircuit<F> {
       prev_c = c_cell;
     }
 
+    let index = self.assign_index(layouter.namespace(|| "index"), n)?;
+
+    Ok((prev_c, index))
+  }
+
+  fn expose_public(
+    &self,
+    layouter: impl Layouter<F>,
+    num: &Self::Num,
+    row: usize
+  ) -> Result<(), Error> {
+    FibonacciChip::expose_public(self, layouter, num, row)
+  }
+}
+
+/// Proves `f(n)` for the sequence seeded with `f(0)` and `f(1)`, laid out by
+/// the chip `C`.
+///
+/// The length and seed mode are fixed at keygen time, so circuits built with
+/// different `n` or [`SeedMode`] get different keys. With private seeds the
+/// proof shows knowledge of seeds reaching `f(n)` after `n` steps.
+#[derive(Clone, Debug)]
+pub struct FiboCircuit<F: PrimeField, C: FibonacciInstructions<F> = FibonacciChip<F>> {
+  pub n: usize,
+  pub mode: SeedMode,
+  /// Seeds, only read in [`SeedMode::Private`].
+  pub f0: Value<F>,
+  pub f1: Value<F>,
+  _marker: PhantomData<C>,
+}
+
+impl<F: PrimeField, C: FibonacciInstructions<F>> FiboCircuit<F, C> {
+  /// Public-seed circuit; `f(0)` and `f(1)` come from the instance column.
+  pub fn new(n: usize) -> Self {
+    assert!(n >= 2, "the sequence needs at least one addition row");
+    Self {
+      n,
+      mode: SeedMode::Public,
+      f0: Value::unknown(),
+      f1: Value::unknown(),
+      _marker: PhantomData,
+    }
+  }
+
+  /// Private-seed circuit; only `f(n)` and `n` are public.
+  pub fn private(n: usize, f0: Value<F>, f1: Value<F>) -> Self {
+    Self {
+      mode: SeedMode::Private,
+      f0,
+      f1,
+      ..Self::new(n)
+    }
+  }
+
+  /// Rows used by the circuit.
+  pub fn rows(&self) -> usize {
+    C::rows(self.n)
+  }
+
+  /// Instance columns for this circuit when seeded with `f0` and `f1`.
+  pub fn instances(&self, f0: F, f1: F) -> Vec<Vec<F>> {
+    fibonacci_instances(self.mode, f0, f1, self.n)
+  }
+
+  /// `k` for `n` steps and the public rows, from a dry run of the layout.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
+impl<F: PrimeField, C: FibonacciInstructions<F>> Circuit<F> for FiboCircuit<F, C> {
+  type Config = C::Config;
+  type FloorPlanner = SimpleFloorPlanner;
+
+  fn without_witnesses(&self) -> Self {
+    Self {
+      n: self.n,
+      mode: self.mode,
+      f0: Value::unknown(),
+      f1: Value::unknown(),
+      _marker: PhantomData,
+    }
+  }
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    C::configure(meta)
+  }
+
+  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let chip = C::construct(config);
+
+    let (out, index) = chip.assign_sequence(
+      layouter.namespace(|| "sequence"),
+      self.mode,
+      self.f0,
+      self.f1,
+      self.n
+    )?;
+
     chip.expose_public(
       layouter.namespace(|| "out"),
-      &prev_c,
-      2
+      &out,
+      self.mode.out_row()
     )?;
+    chip.expose_public(layouter.namespace(|| "n"), &index, self.mode.index_row())?;
 
     Ok(())
   }
 }
 
+/// Reference `f(n)` computed outside the circuit.
+pub fn fibonacci_native<F: PrimeField>(f0: F, f1: F, n: usize) -> F {
+  let (mut a, mut b) = (f0, f1);
+  for _ in 0..n {
+    (a, b) = (b, a + b);
+  }
+  a
+}
+
+/// Instance columns a Fibonacci circuit in `mode` expects for seeds `f0`,
+/// `f1` and length `n`.
+pub fn fibonacci_instances<F: PrimeField>(mode: SeedMode, f0: F, f1: F, n: usize) -> Vec<Vec<F>> {
+  let out = fibonacci_native(f0, f1, n);
+  let index = F::from(n as u64);
+  match mode {
+    SeedMode::Public => vec![vec![f0, f1, out, index]],
+    SeedMode::Private => vec![vec![out, index]],
+  }
+}
+
 #[cfg(test)]
 mod tests {
-  use std::marker::PhantomData;
-
-  use super::FiboCircuit;
+  use super::*;
   use halo2_proofs::{ dev::MockProver, pasta::Fp };
 
   #[test]
@@ -182,16 +452,81 @@ mod tests {
     let a = Fp::from(1); // F[0]
     let b = Fp::from(1); // F[1]
     let out = Fp::from(55); // F[9]
+    let n = Fp::from(9);
 
-    let circuit = FiboCircuit(PhantomData);
+    let circuit = FiboCircuit::<Fp>::new(9);
 
-    let mut public_input = vec![a, b, out];
+    let mut public_input = vec![a, b, out, n];
 
     let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
     prover.assert_satisfied();
 
     public_input[2] += Fp::one();
-    let _prover = MockProver::run(k, &circuit, vec![public_input]).unwrap();
+    let prover = MockProver::run(k, &circuit, vec![public_input]).unwrap();
+    assert!(prover.verify().is_err());
+  }
+
+  #[test]
+  fn fibonacci_lengths() {
+    for n in [2, 3, 10, 20, 50, 100] {
+      let circuit = FiboCircuit::<Fp>::new(n);
+      let k = circuit.k();
+      if n >= 20 {
+        assert!(k > 4);
+      }
+
+      let prover = MockProver::run(k, &circuit, circuit.instances(Fp::one(), Fp::one())).unwrap();
+      prover.assert_satisfied();
+    }
+  }
+
+  #[test]
+  fn fibonacci_wrong_index() {
+    let circuit = FiboCircuit::<Fp>::new(20);
+
+    let mut instances = circuit.instances(Fp::one(), Fp::one());
+    instances[0][INDEX_ROW] += Fp::one();
+    let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+    assert!(prover.verify().is_err());
+  }
+
+  #[test]
+  fn fibonacci_private_seeds() {
+    for (f0, f1, n) in [(1, 1, 9), (2, 1, 10), (3, 7, 40)] {
+      let (seed0, seed1) = (Value::known(Fp::from(f0)), Value::known(Fp::from(f1)));
+      let circuit = FiboCircuit::<Fp>::private(n, seed0, seed1);
+
+      let instances = circuit.instances(Fp::from(f0), Fp::from(f1));
+      let prover = MockProver::run(circuit.k(), &circuit, instances.clone()).unwrap();
+      prover.assert_satisfied();
+
+      // Seeds that do not reach the claimed output.
+      let wrong = FiboCircuit::<Fp>::private(n, seed0 + Value::known(Fp::one()), seed1);
+      let prover = MockProver::run(circuit.k(), &wrong, instances).unwrap();
+      assert!(prover.verify().is_err());
+    }
+  }
+
+  #[test]
+  fn fibonacci_native_matches_circuit() {
+    for (f0, f1) in [(0, 1), (1, 1), (2, 1), (7, 3)] {
+      for n in 2..30 {
+        let (f0, f1) = (Fp::from(f0), Fp::from(f1));
+        for mode in [SeedMode::Public, SeedMode::Private] {
+          let circuit = match mode {
+            SeedMode::Public => FiboCircuit::<Fp>::new(n),
+            SeedMode::Private => FiboCircuit::<Fp>::private(n, Value::known(f0), Value::known(f1)),
+          };
+          let mut instances = circuit.instances(f0, f1);
+          let prover = MockProver::run(circuit.k(), &circuit, instances.clone()).unwrap();
+          prover.assert_satisfied();
+
+          instances[0][mode.out_row()] = fibonacci_native(f0, f1, n + 1);
+          let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+          assert!(prover.verify().is_err());
+        }
+      }
+    }
   }
 
   #[cfg(feature = "dev-graph")]
@@ -203,7 +538,7 @@ mod tests {
     root.fill(&WHITE).unwrap();
     let root = root.titled("Fib 1 Layout", ("sans-serif", 60)).unwrap();
 
-    let circuit = FiboCircuit::<Fp>(PhantomData);
+    let circuit = FiboCircuit::<Fp>::new(9);
     halo2_proofs::dev::CircuitLayout::default().render(4, &circuit, &root).unwrap();
   }
 }
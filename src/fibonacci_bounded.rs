@@ -0,0 +1,250 @@
+use std::marker::PhantomData;
+use halo2_proofs::{ circuit::*, plonk::* };
+
+use group::ff::PrimeField;
+
+use crate::{
+  fibonacci::{ fibonacci_instances, FibonacciChip, FibonacciConfig, FibonacciInstructions, SeedMode },
+  range_check_dynamic::{ DynamicRangeChip, DynamicRangeConfig },
+  stats::k_for,
+};
+
+/// The Fibonacci chip and the range check, sharing one instance column, one
+/// constant column and two of the three advice columns.
+#[derive(Clone, Debug)]
+pub struct BoundedFibonacciConfig {
+  fibonacci: FibonacciConfig,
+  range: DynamicRangeConfig,
+}
+
+/// Proves `f(n)` like [`crate::fibonacci::FiboCircuit`], and that it is
+/// below a public bound. `f(n)` must fit in `NUM_BITS` bits, which are
+/// checked in `K`-bit words, and the bound must be between 1 and
+/// `2^NUM_BITS`: larger bounds are rejected by
+/// [`DynamicRangeChip::assign_bound`] whatever `f(n)` is.
+///
+/// The bound follows the Fibonacci public inputs on the same instance
+/// column, at [`Self::bound_row`]: `[f(0), f(1), f(n), n, bound]` with
+/// public seeds and `[f(n), n, bound]` with private ones.
+#[derive(Clone, Debug)]
+pub struct BoundedFiboCircuit<F: PrimeField, const K: usize, const NUM_BITS: usize> {
+  pub n: usize,
+  pub mode: SeedMode,
+  /// Seeds, only read in [`SeedMode::Private`].
+  pub f0: Value<F>,
+  pub f1: Value<F>,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField, const K: usize, const NUM_BITS: usize> BoundedFiboCircuit<F, K, NUM_BITS> {
+  /// Public-seed circuit; `f(0)` and `f(1)` come from the instance column.
+  pub fn new(n: usize) -> Self {
+    assert!(n >= 2, "the sequence needs at least one addition row");
+    Self {
+      n,
+      mode: SeedMode::Public,
+      f0: Value::unknown(),
+      f1: Value::unknown(),
+      _marker: PhantomData,
+    }
+  }
+
+  /// Private-seed circuit; only `f(n)`, `n` and the bound are public.
+  pub fn private(n: usize, f0: Value<F>, f1: Value<F>) -> Self {
+    Self {
+      mode: SeedMode::Private,
+      f0,
+      f1,
+      ..Self::new(n)
+    }
+  }
+
+  pub fn bound_row(&self) -> usize {
+    self.mode.index_row() + 1
+  }
+
+  /// Instance column for this circuit when seeded with `f0` and `f1`.
+  pub fn instances(&self, f0: F, f1: F, bound: F) -> Vec<Vec<F>> {
+    let mut instances = fibonacci_instances(self.mode, f0, f1, self.n);
+    instances[0].push(bound);
+    instances
+  }
+
+  /// `k` for the sequence, both decompositions and the word table.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
+impl<F: PrimeField, const K: usize, const NUM_BITS: usize> Circuit<F>
+for BoundedFiboCircuit<F, K, NUM_BITS> {
+  type Config = BoundedFibonacciConfig;
+  type FloorPlanner = SimpleFloorPlanner;
+
+  fn without_witnesses(&self) -> Self {
+    Self {
+      n: self.n,
+      mode: self.mode,
+      f0: Value::unknown(),
+      f1: Value::unknown(),
+      _marker: PhantomData,
+    }
+  }
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+    let instance = meta.instance_column();
+    let constant = meta.fixed_column();
+
+    // Regions never overlap in a column, so the range check can reuse the
+    // Fibonacci columns; its gates are all behind its own selectors.
+    BoundedFibonacciConfig {
+      fibonacci: FibonacciChip::configure_with_columns(meta, advice, instance, constant),
+      range: DynamicRangeChip::<F, K>::configure_with_columns(meta, NUM_BITS, advice[0], instance, advice[1]),
+    }
+  }
+
+  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let fibonacci = FibonacciChip::construct(config.fibonacci);
+    let range = DynamicRangeChip::<F, K>::construct(config.range);
+    range.load(layouter.namespace(|| "word table"))?;
+
+    let (out, index) = fibonacci.assign_sequence(
+      layouter.namespace(|| "sequence"),
+      self.mode,
+      self.f0,
+      self.f1,
+      self.n
+    )?;
+
+    let bound = range.assign_bound(layouter.namespace(|| "bound"), self.bound_row())?;
+    range.check_cell(layouter.namespace(|| "f(n) < bound"), &out, &bound)?;
+
+    fibonacci.expose_public(layouter.namespace(|| "out"), &out, self.mode.out_row())?;
+    fibonacci.expose_public(layouter.namespace(|| "n"), &index, self.mode.index_row())?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{ fibonacci::{ fibonacci_native, FiboCircuit, OUT_ROW }, stats::CircuitStats };
+  use halo2_proofs::{ dev::{ FailureLocation, MockProver, VerifyFailure }, pasta::Fp, plonk::Any };
+
+  type Bounded = BoundedFiboCircuit<Fp, 8, 64>;
+
+  fn failures(circuit: &Bounded, instances: Vec<Vec<Fp>>) -> Vec<VerifyFailure> {
+    MockProver::run(circuit.k(), circuit, instances).unwrap().verify().err().unwrap_or_default()
+  }
+
+  /// Names of the regions the failures are located in.
+  fn failed_regions(failures: &[VerifyFailure]) -> Vec<String> {
+    failures
+      .iter()
+      .filter_map(|failure| match failure {
+        VerifyFailure::ConstraintNotSatisfied { location, .. }
+        | VerifyFailure::Lookup { location, .. }
+        | VerifyFailure::Permutation { location, .. } => Some(location),
+        _ => None,
+      })
+      .filter_map(|location| match location {
+        FailureLocation::InRegion { region, .. } => Some(format!("{}", region)),
+        FailureLocation::OutsideRegion { .. } => None,
+      })
+      .collect()
+  }
+
+  /// The decomposition regions of the range check for `n = 9`: region 11
+  /// checks `bound − 1` and region 16 checks `bound − 1 − f(n)`.
+  const BOUND_REGION: usize = 11;
+  const DIFF_REGION: usize = 16;
+
+  /// A strict 64-bit decomposition in `region` whose last word, at offset
+  /// 8, is not zero.
+  fn last_word(region: usize) -> VerifyFailure {
+    VerifyFailure::Permutation {
+      column: (Any::Advice, 1).into(),
+      location: FailureLocation::InRegion { region: (region, "decompose").into(), offset: 8 },
+    }
+  }
+
+  /// The zero constant that last word is copied from.
+  fn zero_constant(row: usize) -> VerifyFailure {
+    VerifyFailure::Permutation {
+      column: (Any::Fixed, 0).into(),
+      location: FailureLocation::OutsideRegion { row },
+    }
+  }
+
+  #[test]
+  fn bounded_fibonacci_within_bound() {
+    let one = Fp::one();
+    for n in [2, 9, 50, 90] {
+      let circuit = Bounded::new(n);
+      let out = fibonacci_native(one, one, n);
+
+      // The tightest bound and the loosest 64-bit one.
+      for bound in [out + one, Fp::from(u64::MAX)] {
+        assert_eq!(failures(&circuit, circuit.instances(one, one, bound)), vec![]);
+      }
+    }
+
+    let (f0, f1) = (Fp::from(3), Fp::from(4));
+    let circuit = Bounded::private(20, Value::known(f0), Value::known(f1));
+    let bound = fibonacci_native(f0, f1, 20) + Fp::from(1000);
+    assert_eq!(failures(&circuit, circuit.instances(f0, f1, bound)), vec![]);
+  }
+
+  #[test]
+  fn bounded_fibonacci_over_bound() {
+    let one = Fp::one();
+    let circuit = Bounded::new(9);
+    let out = fibonacci_native(one, one, 9);
+
+    // f(9) = 55 is computed correctly but is not below 55 or 10, so
+    // `bound − 1 − f(n)` wraps and does not fit in 64 bits.
+    for bound in [out, Fp::from(10)] {
+      assert_eq!(
+        failures(&circuit, circuit.instances(one, one, bound)),
+        vec![last_word(DIFF_REGION), zero_constant(3)]
+      );
+    }
+
+    // Bounds past 2^64 are rejected even though f(9) is far below them:
+    // `bound − 1` itself does not fit.
+    let two_pow_64 = Fp::from_u128(1 << 64);
+    assert_eq!(failures(&circuit, circuit.instances(one, one, two_pow_64)), vec![]);
+    assert_eq!(
+      failures(&circuit, circuit.instances(one, one, two_pow_64 + one)),
+      vec![last_word(BOUND_REGION), zero_constant(1)]
+    );
+    assert_eq!(
+      failures(&circuit, circuit.instances(one, one, Fp::from_u128(u128::MAX))),
+      vec![last_word(BOUND_REGION), last_word(DIFF_REGION), zero_constant(1), zero_constant(3)]
+    );
+
+    // A wrong f(n) under a loose bound fails on the Fibonacci side instead.
+    let mut instances = circuit.instances(one, one, Fp::from(1000));
+    instances[0][OUT_ROW] += one;
+    let failures = failures(&circuit, instances);
+    assert!(!failures.is_empty());
+    assert!(failed_regions(&failures).iter().all(|region| !region.contains("decompose")));
+  }
+
+  #[test]
+  fn bounded_fibonacci_shares_columns() {
+    let n = 20;
+    let one = Fp::one();
+    let bounded = Bounded::new(n);
+    let plain = FiboCircuit::<Fp>::new(n);
+
+    let bounded_stats = CircuitStats::measure(&bounded, &bounded.instances(one, one, Fp::from(1 << 20))).unwrap();
+    let plain_stats = CircuitStats::measure(&plain, &plain.instances(one, one)).unwrap();
+
+    assert_eq!(bounded_stats.advice_columns, plain_stats.advice_columns);
+    assert_eq!(bounded_stats.instance_columns, 1);
+    assert_eq!(bounded_stats.k, bounded.k());
+  }
+}
@@ -0,0 +1,402 @@
+use std::marker::PhantomData;
+use halo2_proofs::{ circuit::*, plonk::*, poly::Rotation };
+
+use group::ff::PrimeField;
+
+use crate::{
+  fibonacci::{ FibonacciInstructions, SeedMode, F0_ROW, F1_ROW, INDEX_ROW, OUT_ROW },
+  stats::k_for,
+};
+
+/// Fibonacci by fast doubling. Each row holds `(F(k), F(k+1))` for the
+/// standard sequence and the prefix `k` of `n`'s bits read so far, most
+/// significant bit first. One step maps `k` to `2k + bit` using
+///
+/// - `F(2k) = F(k) · (2F(k+1) − F(k))`
+/// - `F(2k+1) = F(k)² + F(k+1)²`
+///
+/// so `n < 2^bits` takes `bits` rows. The seeded output is then
+/// `f(n) = f(0) · F(n−1) + f(1) · F(n)`, with `F(n−1) = F(n+1) − F(n)`.
+#[derive(Clone, Copy, Debug)]
+pub struct FastFibonacciConfig {
+  bit: Column<Advice>,
+  col_a: Column<Advice>,
+  col_b: Column<Advice>,
+  acc: Column<Advice>,
+  q_step: Selector,
+  q_out: Selector,
+  instance: Column<Instance>,
+}
+
+/// The cells holding `f(n)` and `n`.
+type Output<F> = (AssignedCell<F, F>, AssignedCell<F, F>);
+
+#[derive(Debug, Clone)]
+pub struct FastFibonacciChip<F: PrimeField> {
+  config: FastFibonacciConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> FastFibonacciChip<F> {
+  pub fn construct(config: FastFibonacciConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>) -> FastFibonacciConfig {
+    let bit = meta.advice_column();
+    let col_a = meta.advice_column();
+    let col_b = meta.advice_column();
+    let acc = meta.advice_column();
+    let q_step = meta.selector();
+    let q_out = meta.selector();
+    let instance = meta.instance_column();
+    let constant = meta.fixed_column();
+
+    meta.enable_equality(col_a);
+    meta.enable_equality(col_b);
+    meta.enable_equality(acc);
+    meta.enable_equality(instance);
+    meta.enable_constant(constant);
+
+    meta.create_gate("doubling step", |meta| {
+      let q = meta.query_selector(q_step);
+      let bit = meta.query_advice(bit, Rotation::cur());
+      let a = meta.query_advice(col_a, Rotation::cur());
+      let b = meta.query_advice(col_b, Rotation::cur());
+      let acc_cur = meta.query_advice(acc, Rotation::cur());
+      let a_next = meta.query_advice(col_a, Rotation::next());
+      let b_next = meta.query_advice(col_b, Rotation::next());
+      let acc_next = meta.query_advice(acc, Rotation::next());
+
+      let one = Expression::Constant(F::ONE);
+      let two = Expression::Constant(F::from(2));
+      let double = a.clone() * (b.clone() * two.clone() - a.clone());
+      let double_plus_one = a.clone() * a + b.clone() * b;
+      let not_bit = one.clone() - bit.clone();
+
+      Constraints::with_selector(q, [
+        ("bit is boolean", bit.clone() * (one - bit.clone())),
+        (
+          "a' = bit ? F(2k+1) : F(2k)",
+          a_next - (not_bit.clone() * double.clone() + bit.clone() * double_plus_one.clone()),
+        ),
+        (
+          "b' = bit ? F(2k+2) : F(2k+1)",
+          b_next -
+            (not_bit * double_plus_one.clone() + bit.clone() * (double + double_plus_one)),
+        ),
+        ("acc' = 2acc + bit", acc_next - (acc_cur * two + bit)),
+      ])
+    });
+
+    meta.create_gate("seeded output", |meta| {
+      let q = meta.query_selector(q_out);
+      let a = meta.query_advice(col_a, Rotation::cur());
+      let b = meta.query_advice(col_b, Rotation::cur());
+      let f0 = meta.query_advice(col_a, Rotation::next());
+      let f1 = meta.query_advice(col_b, Rotation::next());
+      let out = meta.query_advice(acc, Rotation::next());
+
+      Constraints::with_selector(q, [
+        ("f(n) = f(0)F(n-1) + f(1)F(n)", out - (f0 * (b - a.clone()) + f1 * a)),
+      ])
+    });
+
+    FastFibonacciConfig {
+      bit,
+      col_a,
+      col_b,
+      acc,
+      q_step,
+      q_out,
+      instance,
+    }
+  }
+
+  /// Walks `n`'s `bits` bits from `(F(0), F(1))`, then combines the result
+  /// with the seeds, read from the instance column or witnessed from
+  /// `f0`/`f1` depending on `mode`. Returns the cells holding `f(n)` and `n`.
+  pub fn assign(
+    &self,
+    mut layouter: impl Layouter<F>,
+    bits: usize,
+    n: Value<u64>,
+    mode: SeedMode,
+    f0: Value<F>,
+    f1: Value<F>
+  ) -> Result<Output<F>, Error> {
+    layouter.assign_region(
+      || "doubling",
+      |mut region| {
+        let config = &self.config;
+        let mut a_cell = region.assign_advice_from_constant(|| "F(0)", config.col_a, 0, F::ZERO)?;
+        let mut b_cell = region.assign_advice_from_constant(|| "F(1)", config.col_b, 0, F::ONE)?;
+        let mut acc_cell = region.assign_advice_from_constant(|| "k", config.acc, 0, F::ZERO)?;
+
+        for row in 0..bits {
+          self.config.q_step.enable(&mut region, row)?;
+
+          let bit = n.map(|n| (n >> (bits - 1 - row)) & 1);
+          region.assign_advice(|| "bit", self.config.bit, row, || bit.map(F::from))?;
+
+          let a = a_cell.value().copied();
+          let b = b_cell.value().copied();
+          let double = a * (b + b - a);
+          let double_plus_one = a * a + b * b;
+          let (a_next, b_next) = bit
+            .zip(double.zip(double_plus_one))
+            .map(|(bit, (c, d))| if bit == 1 { (d, c + d) } else { (c, d) })
+            .unzip();
+
+          a_cell = region.assign_advice(|| "F(k)", self.config.col_a, row + 1, || a_next)?;
+          b_cell = region.assign_advice(|| "F(k+1)", self.config.col_b, row + 1, || b_next)?;
+          acc_cell = region.assign_advice(
+            || "k",
+            self.config.acc,
+            row + 1,
+            || acc_cell.value().copied().zip(bit).map(|(acc, bit)| acc.double() + F::from(bit))
+          )?;
+        }
+
+        self.config.q_out.enable(&mut region, bits)?;
+
+        let (f0, f1) = match mode {
+          SeedMode::Public =>
+            (
+              region.assign_advice_from_instance(
+                || "f(0)",
+                config.instance,
+                F0_ROW,
+                config.col_a,
+                bits + 1
+              )?,
+              region.assign_advice_from_instance(
+                || "f(1)",
+                config.instance,
+                F1_ROW,
+                config.col_b,
+                bits + 1
+              )?,
+            ),
+          SeedMode::Private =>
+            (
+              region.assign_advice(|| "f(0)", config.col_a, bits + 1, || f0)?,
+              region.assign_advice(|| "f(1)", config.col_b, bits + 1, || f1)?,
+            ),
+        };
+
+        let f_n = a_cell.value().copied();
+        let f_n_minus_one = b_cell.value().copied() - f_n;
+        let out = region.assign_advice(
+          || "f(n)",
+          self.config.acc,
+          bits + 1,
+          || f0.value().copied() * f_n_minus_one + f1.value().copied() * f_n
+        )?;
+
+        Ok((out, acc_cell))
+      }
+    )
+  }
+
+  pub fn expose_public(
+    &self,
+    mut layouter: impl Layouter<F>,
+    cell: &AssignedCell<F, F>,
+    row: usize
+  ) -> Result<(), Error> {
+    layouter.constrain_instance(cell.cell(), self.config.instance, row)
+  }
+}
+
+impl<F: PrimeField> Chip<F> for FastFibonacciChip<F> {
+  type Config = FastFibonacciConfig;
+  type Loaded = ();
+
+  fn config(&self) -> &Self::Config {
+    &self.config
+  }
+
+  fn loaded(&self) -> &Self::Loaded {
+    &()
+  }
+}
+
+/// With the trait, `n` is fixed at keygen like the other layouts and the
+/// walk uses exactly `n`'s bit length.
+impl<F: PrimeField> FibonacciInstructions<F> for FastFibonacciChip<F> {
+  type Num = AssignedCell<F, F>;
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    FastFibonacciChip::configure(meta)
+  }
+
+  fn construct(config: Self::Config) -> Self {
+    FastFibonacciChip::construct(config)
+  }
+
+  fn rows(n: usize) -> usize {
+    bit_length(n) + 2
+  }
+
+  fn assign_sequence(
+    &self,
+    layouter: impl Layouter<F>,
+    mode: SeedMode,
+    f0: Value<F>,
+    f1: Value<F>,
+    n: usize
+  ) -> Result<(Self::Num, Self::Num), Error> {
+    self.assign(layouter, bit_length(n), Value::known(n as u64), mode, f0, f1)
+  }
+
+  fn expose_public(
+    &self,
+    layouter: impl Layouter<F>,
+    num: &Self::Num,
+    row: usize
+  ) -> Result<(), Error> {
+    FastFibonacciChip::expose_public(self, layouter, num, row)
+  }
+}
+
+fn bit_length(n: usize) -> usize {
+  (usize::BITS - n.leading_zeros()) as usize
+}
+
+/// Same statement and public inputs as [`crate::fibonacci::FiboCircuit`],
+/// `[f(0), f(1), f(n), n]`, in `bits + 2` rows. `n` is a witness, so one key
+/// covers every `n < 2^bits`.
+#[derive(Clone, Debug)]
+pub struct FastFiboCircuit<F> {
+  pub bits: usize,
+  pub n: Value<u64>,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> FastFiboCircuit<F> {
+  pub fn new(bits: usize, n: u64) -> Self {
+    assert!(bits <= 64, "n is a u64");
+    assert!(bits == 64 || n >> bits == 0, "n does not fit in {} bits", bits);
+    Self {
+      bits,
+      n: Value::known(n),
+      _marker: PhantomData,
+    }
+  }
+
+  /// Rows used by the circuit: one per bit, the final state and the output.
+  pub fn rows(&self) -> usize {
+    self.bits + 2
+  }
+
+  /// `k` for `bits` doubling rows and the public rows.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
+impl<F: PrimeField> Circuit<F> for FastFiboCircuit<F> {
+  type Config = FastFibonacciConfig;
+  type FloorPlanner = SimpleFloorPlanner;
+
+  fn without_witnesses(&self) -> Self {
+    Self {
+      bits: self.bits,
+      n: Value::unknown(),
+      _marker: PhantomData,
+    }
+  }
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    FastFibonacciChip::configure(meta)
+  }
+
+  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let chip = FastFibonacciChip::construct(config);
+
+    let (out, index) = chip.assign(
+      layouter.namespace(|| "doubling"),
+      self.bits,
+      self.n,
+      SeedMode::Public,
+      Value::unknown(),
+      Value::unknown()
+    )?;
+    chip.expose_public(layouter.namespace(|| "out"), &out, OUT_ROW)?;
+    chip.expose_public(layouter.namespace(|| "n"), &index, INDEX_ROW)?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fibonacci::{ fibonacci_instances, FiboCircuit };
+  use halo2_proofs::{ dev::MockProver, pasta::Fp };
+
+  #[test]
+  fn fast_fibonacci_matches_fibo_circuit() {
+    let bits = 6;
+    for n in 2..40u64 {
+      let slow = FiboCircuit::<Fp>::new(n as usize);
+      let fast = FastFiboCircuit::<Fp>::new(bits, n);
+
+      let instances = slow.instances(Fp::from(2), Fp::from(5));
+      MockProver::run(slow.k(), &slow, instances.clone()).unwrap().assert_satisfied();
+      MockProver::run(fast.k(), &fast, instances).unwrap().assert_satisfied();
+    }
+  }
+
+  #[test]
+  fn fast_fibonacci_as_fibonacci_instructions() {
+    for n in [2, 9, 31, 32, 1000] {
+      let circuit = FiboCircuit::<Fp, FastFibonacciChip<Fp>>::new(n);
+      let instances = circuit.instances(Fp::one(), Fp::one());
+      let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+      prover.assert_satisfied();
+
+      let (f0, f1) = (Value::known(Fp::from(4)), Value::known(Fp::from(9)));
+      let circuit = FiboCircuit::<Fp, FastFibonacciChip<Fp>>::private(n, f0, f1);
+      let instances = circuit.instances(Fp::from(4), Fp::from(9));
+      let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+      prover.assert_satisfied();
+    }
+  }
+
+  #[test]
+  fn fast_fibonacci_large_n() {
+    let n = 1 << 20;
+    let circuit = FastFiboCircuit::<Fp>::new(21, n);
+
+    let mut instances = fibonacci_instances(SeedMode::Public, Fp::one(), Fp::one(), n as usize);
+    let prover = MockProver::run(circuit.k(), &circuit, instances.clone()).unwrap();
+    prover.assert_satisfied();
+
+    instances[0][OUT_ROW] += Fp::one();
+    let prover = MockProver::run(circuit.k(), &circuit, instances.clone()).unwrap();
+    assert!(prover.verify().is_err());
+
+    instances[0][OUT_ROW] -= Fp::one();
+    instances[0][INDEX_ROW] += Fp::one();
+    let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+    assert!(prover.verify().is_err());
+  }
+
+  #[test]
+  fn compare_rows() {
+    // The README table: one row per step against one per bit of `n`, the
+    // final state and the output.
+    for log_n in [4, 8, 12, 16, 20] {
+      let n = 1usize << log_n;
+      let slow = FiboCircuit::<Fp>::new(n);
+      let fast = FastFiboCircuit::<Fp>::new(log_n + 1, n as u64);
+      assert_eq!(slow.rows(), n);
+      assert_eq!(fast.rows(), log_n + 3);
+    }
+  }
+}
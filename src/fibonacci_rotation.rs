@@ -0,0 +1,264 @@
+use std::marker::PhantomData;
+use halo2_proofs::{ circuit::*, plonk::*, poly::Rotation };
+
+use group::ff::PrimeField;
+
+use crate::fibonacci::{ FiboCircuit, FibonacciInstructions, SeedMode, F0_ROW, F1_ROW };
+
+/// Fibonacci layout that keeps the whole sequence in one advice column of a
+/// single region. The gate reads three consecutive rows, so no copy
+/// constraints are needed between steps.
+#[derive(Clone, Copy, Debug)]
+pub struct FibonacciRotationConfig {
+  advice: Column<Advice>,
+  selector: Selector,
+  instance: Column<Instance>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FibonacciRotationChip<F: PrimeField> {
+  config: FibonacciRotationConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> FibonacciRotationChip<F> {
+  pub fn construct(config: FibonacciRotationConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>) -> FibonacciRotationConfig {
+    let advice = meta.advice_column();
+    let selector = meta.selector();
+    let instance = meta.instance_column();
+    let constant = meta.fixed_column();
+
+    meta.enable_equality(advice);
+    meta.enable_equality(instance);
+    meta.enable_constant(constant);
+
+    meta.create_gate("add", |meta| {
+      let s = meta.query_selector(selector);
+      let a = meta.query_advice(advice, Rotation::cur());
+      let b = meta.query_advice(advice, Rotation::next());
+      let c = meta.query_advice(advice, Rotation(2));
+      vec![s * (a + b - c)]
+    });
+
+    FibonacciRotationConfig {
+      advice,
+      selector,
+      instance,
+    }
+  }
+
+  /// Assigns `f(0)..=f(n)` down the advice column and returns the cell
+  /// holding `f(n)`. Seeds are read from the instance column or witnessed
+  /// from `f0`/`f1` depending on `mode`.
+  pub fn assign(
+    &self,
+    mut layouter: impl Layouter<F>,
+    mode: SeedMode,
+    f0: Value<F>,
+    f1: Value<F>,
+    n: usize
+  ) -> Result<AssignedCell<F, F>, Error> {
+    layouter.assign_region(
+      || "sequence",
+      |mut region| {
+        let (mut a_cell, mut b_cell) = match mode {
+          SeedMode::Public =>
+            (
+              region.assign_advice_from_instance(
+                || "f(0)",
+                self.config.instance,
+                F0_ROW,
+                self.config.advice,
+                0
+              )?,
+              region.assign_advice_from_instance(
+                || "f(1)",
+                self.config.instance,
+                F1_ROW,
+                self.config.advice,
+                1
+              )?,
+            ),
+          SeedMode::Private =>
+            (
+              region.assign_advice(|| "f(0)", self.config.advice, 0, || f0)?,
+              region.assign_advice(|| "f(1)", self.config.advice, 1, || f1)?,
+            ),
+        };
+
+        for row in 2..=n {
+          self.config.selector.enable(&mut region, row - 2)?;
+
+          let c_cell = region.assign_advice(
+            || "c",
+            self.config.advice,
+            row,
+            || a_cell.value().copied() + b_cell.value()
+          )?;
+          a_cell = b_cell;
+          b_cell = c_cell;
+        }
+
+        Ok(b_cell)
+      }
+    )
+  }
+
+  /// Fixes the sequence index `n` as a constant so it can be exposed as a
+  /// public input next to `f(n)`.
+  pub fn assign_index(
+    &self,
+    mut layouter: impl Layouter<F>,
+    n: usize
+  ) -> Result<AssignedCell<F, F>, Error> {
+    layouter.assign_region(
+      || "index",
+      |mut region| {
+        region.assign_advice_from_constant(
+          || "n",
+          self.config.advice,
+          0,
+          F::from(n as u64)
+        )
+      }
+    )
+  }
+
+  pub fn expose_public(
+    &self,
+    mut layouter: impl Layouter<F>,
+    cell: &AssignedCell<F, F>,
+    row: usize
+  ) -> Result<(), Error> {
+    layouter.constrain_instance(cell.cell(), self.config.instance, row)
+  }
+}
+
+impl<F: PrimeField> Chip<F> for FibonacciRotationChip<F> {
+  type Config = FibonacciRotationConfig;
+  type Loaded = ();
+
+  fn config(&self) -> &Self::Config {
+    &self.config
+  }
+
+  fn loaded(&self) -> &Self::Loaded {
+    &()
+  }
+}
+
+impl<F: PrimeField> FibonacciInstructions<F> for FibonacciRotationChip<F> {
+  type Num = AssignedCell<F, F>;
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    FibonacciRotationChip::configure(meta)
+  }
+
+  fn construct(config: Self::Config) -> Self {
+    FibonacciRotationChip::construct(config)
+  }
+
+  /// `f(0)..=f(n)` plus the index row.
+  fn rows(n: usize) -> usize {
+    n + 2
+  }
+
+  fn assign_sequence(
+    &self,
+    mut layouter: impl Layouter<F>,
+    mode: SeedMode,
+    f0: Value<F>,
+    f1: Value<F>,
+    n: usize
+  ) -> Result<(Self::Num, Self::Num), Error> {
+    let out = self.assign(layouter.namespace(|| "sequence"), mode, f0, f1, n)?;
+    let index = self.assign_index(layouter.namespace(|| "index"), n)?;
+
+    Ok((out, index))
+  }
+
+  fn expose_public(
+    &self,
+    layouter: impl Layouter<F>,
+    num: &Self::Num,
+    row: usize
+  ) -> Result<(), Error> {
+    FibonacciRotationChip::expose_public(self, layouter, num, row)
+  }
+}
+
+/// [`FiboCircuit`] laid out with [`FibonacciRotationChip`].
+pub type FiboRotationCircuit<F> = FiboCircuit<F, FibonacciRotationChip<F>>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{ fibonacci::OUT_ROW, prover::{ prove, setup, verify }, stats::CircuitStats };
+  use halo2_proofs::{ dev::MockProver, pasta::Fp };
+
+  #[test]
+  fn fibonacci_rotation_example() {
+    for n in [2, 9, 20, 100] {
+      let circuit = FiboRotationCircuit::<Fp>::new(n);
+
+      let mut instances = circuit.instances(Fp::one(), Fp::one());
+
+      let prover = MockProver::run(circuit.k(), &circuit, instances.clone()).unwrap();
+      prover.assert_satisfied();
+
+      instances[0][OUT_ROW] += Fp::one();
+      let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+      assert!(prover.verify().is_err());
+    }
+  }
+
+  #[test]
+  fn fibonacci_rotation_private_seeds() {
+    let (f0, f1) = (Value::known(Fp::from(3)), Value::known(Fp::from(4)));
+    let circuit = FiboRotationCircuit::private(20, f0, f1);
+
+    let instances = circuit.instances(Fp::from(3), Fp::from(4));
+    let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+    prover.assert_satisfied();
+  }
+
+  /// Length of a real proof for `circuit` seeded with ones, checking it
+  /// verifies.
+  fn proof_len<C: FibonacciInstructions<Fp>>(k: u32, circuit: FiboCircuit<Fp, C>) -> usize {
+    let instances = circuit.instances(Fp::one(), Fp::one());
+    let (params, pk) = setup(k, &circuit).unwrap();
+    let proof = prove(&params, &pk, circuit, &instances).unwrap();
+    assert!(verify(&params, pk.get_vk(), &proof, &instances));
+    proof.len()
+  }
+
+  #[test]
+  fn compare_layouts() {
+    let n = 100;
+    let one = Fp::one();
+    let copy = FiboCircuit::<Fp>::new(n);
+    let rotation = FiboRotationCircuit::<Fp>::new(n);
+    let copy_stats = CircuitStats::measure(&copy, &copy.instances(one, one)).unwrap();
+    let rotation_stats = CircuitStats::measure(&rotation, &rotation.instances(one, one)).unwrap();
+
+    // One row per step against one row per term plus the index row.
+    assert_eq!(copy_stats.usage.rows, n);
+    assert_eq!(rotation_stats.usage.rows, n + 2);
+    // Three advice columns and the instance and constant columns against one
+    // advice column.
+    assert_eq!(copy_stats.permutation_columns, 5);
+    assert_eq!(rotation_stats.permutation_columns, 3);
+    assert_eq!(copy_stats.k, rotation_stats.k);
+    assert!(rotation_stats.proof.bytes < copy_stats.proof.bytes);
+
+    // Real proofs at the same `k` keep the order.
+    assert!(proof_len(rotation_stats.k, rotation) < proof_len(copy_stats.k, copy));
+  }
+}
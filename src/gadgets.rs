@@ -0,0 +1,23 @@
+//! Chips to build other circuits from.
+//!
+//! Every chip follows the same pattern: `configure` allocates the columns,
+//! selectors and tables it needs and returns a config whose layout stays
+//! private, `construct` wraps that config, and the `assign` and
+//! `check_cell` style methods return `AssignedCell`s that other chips can
+//! copy from. Chips with a lookup table also have a `load` method to call
+//! once per circuit.
+
+pub use crate::{
+  bits::{ AssignedBits, BitsChip, BitsConfig },
+  fibonacci::{ FibonacciChip, FibonacciConfig, FibonacciInstructions, SeedMode },
+  fibonacci_fast::{ FastFibonacciChip, FastFibonacciConfig },
+  fibonacci_rotation::{ FibonacciRotationChip, FibonacciRotationConfig },
+  is_zero::{ IsEqualChip, IsEqualConfig, IsZeroChip, IsZeroConfig },
+  less_than::{ CheckedCell, LtChip, LtConfig },
+  range_check::{ RangeChip, RangeConfig, RangeStrategy, DEFAULT_MAX_DEGREE },
+  range_check_decompose::{ DecomposeChip, DecomposeConfig, RunningSum },
+  range_check_dynamic::{ DynamicRangeChip, DynamicRangeConfig },
+  range_check_interval::{ DecomposeIntervalChip, DecomposeIntervalConfig, IntervalChip, IntervalConfig },
+  range_check_lookup::{ RangeLookupChip, RangeLookupConfig },
+  recurrence::{ RecurrenceChip, RecurrenceConfig },
+};
@@ -0,0 +1,336 @@
+//! Circuit inputs read from JSON or TOML files.
+//!
+//! A file names its circuit in `circuit` and gives that circuit's
+//! parameters. Field elements are strings or integers: decimal (`"55"`,
+//! `55`), `0x`-prefixed big-endian hex (`"0x37"`), or either with a leading
+//! minus for the additive inverse (`"-1"` is `p − 1`). Values must be below
+//! the field modulus.
+//!
+//! ```toml
+//! circuit = "fibonacci"
+//! n = 9
+//! f0 = 1          # optional, default 1
+//! f1 = "0x1"      # optional, default 1
+//! private = false # optional: keep the seeds out of the public inputs
+//! out = "55"      # optional: claimed f(n), computed when omitted
+//! ```
+//!
+//! ```json
+//! { "circuit": "range", "range": 8, "value": "7" }
+//! ```
+
+use std::{ fmt, fs, io, path::Path };
+
+use group::ff::PrimeField;
+use halo2_proofs::{ circuit::Value, pasta::Fp };
+use serde::{ de, Deserialize, Deserializer };
+
+use crate::{
+  envelope::fp_from_hex,
+  fibonacci::{ fibonacci_instances, FiboCircuit, SeedMode },
+  range_check::RangeCircuit,
+};
+
+#[derive(Debug)]
+pub enum InputError {
+  Io(io::Error),
+  Json(serde_json::Error),
+  Toml(toml::de::Error),
+  /// A field element that could not be parsed.
+  Field(String),
+  /// Parameters the circuit cannot be built with.
+  Invalid(String),
+}
+
+impl fmt::Display for InputError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      InputError::Io(err) => write!(f, "cannot read input: {}", err),
+      InputError::Json(err) => write!(f, "invalid JSON input: {}", err),
+      InputError::Toml(err) => write!(f, "invalid TOML input: {}", err),
+      InputError::Field(value) => write!(f, "invalid field element `{}`", value),
+      InputError::Invalid(reason) => write!(f, "invalid input: {}", reason),
+    }
+  }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<io::Error> for InputError {
+  fn from(err: io::Error) -> Self {
+    InputError::Io(err)
+  }
+}
+
+impl From<serde_json::Error> for InputError {
+  fn from(err: serde_json::Error) -> Self {
+    InputError::Json(err)
+  }
+}
+
+impl From<toml::de::Error> for InputError {
+  fn from(err: toml::de::Error) -> Self {
+    InputError::Toml(err)
+  }
+}
+
+/// Parses a decimal, `0x` hex or negated field element.
+pub fn parse_field(input: &str) -> Result<Fp, InputError> {
+  let invalid = || InputError::Field(input.to_string());
+  let trimmed = input.trim();
+
+  if let Some(abs) = trimmed.strip_prefix('-') {
+    if abs.starts_with('-') {
+      return Err(invalid());
+    }
+    return parse_field(abs).map(|value| -value).map_err(|_| invalid());
+  }
+  if trimmed.starts_with("0x") {
+    return fp_from_hex(trimmed).ok_or_else(invalid);
+  }
+  if trimmed.is_empty() || !trimmed.bytes().all(|b| b.is_ascii_digit()) {
+    return Err(invalid());
+  }
+
+  // Little-endian base-256 digits of the value, checked for overflow.
+  let mut repr = <Fp as PrimeField>::Repr::default();
+  for digit in trimmed.bytes().map(|b| (b - b'0') as u16) {
+    let mut carry = digit;
+    for byte in repr.as_mut().iter_mut() {
+      let wide = (*byte as u16) * 10 + carry;
+      *byte = wide as u8;
+      carry = wide >> 8;
+    }
+    if carry != 0 {
+      return Err(invalid());
+    }
+  }
+  Option::from(Fp::from_repr(repr)).ok_or_else(invalid)
+}
+
+/// A field element in an input file; see the [module docs](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldElement(pub Fp);
+
+impl<'de> Deserialize<'de> for FieldElement {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+      Signed(i64),
+      Unsigned(u64),
+      Text(String),
+    }
+
+    let value = match Raw::deserialize(deserializer)? {
+      Raw::Signed(v) if v < 0 => -Fp::from(v.unsigned_abs()),
+      Raw::Signed(v) => Fp::from(v as u64),
+      Raw::Unsigned(v) => Fp::from(v),
+      Raw::Text(text) => parse_field(&text).map_err(de::Error::custom)?,
+    };
+    Ok(FieldElement(value))
+  }
+}
+
+fn one() -> FieldElement {
+  FieldElement(Fp::one())
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FibonacciInput {
+  pub n: usize,
+  #[serde(default = "one")]
+  pub f0: FieldElement,
+  #[serde(default = "one")]
+  pub f1: FieldElement,
+  #[serde(default)]
+  pub private: bool,
+  /// Claimed `f(n)`; the honest value is used when absent.
+  #[serde(default)]
+  pub out: Option<FieldElement>,
+}
+
+impl FibonacciInput {
+  pub fn mode(&self) -> SeedMode {
+    if self.private { SeedMode::Private } else { SeedMode::Public }
+  }
+
+  pub fn circuit(&self) -> Result<FiboCircuit<Fp>, InputError> {
+    if self.n < 2 {
+      return Err(InputError::Invalid("fibonacci needs n >= 2".to_string()));
+    }
+    Ok(match self.mode() {
+      SeedMode::Public => FiboCircuit::new(self.n),
+      SeedMode::Private =>
+        FiboCircuit::private(self.n, Value::known(self.f0.0), Value::known(self.f1.0)),
+    })
+  }
+
+  pub fn instances(&self) -> Vec<Vec<Fp>> {
+    let mut instances = fibonacci_instances(self.mode(), self.f0.0, self.f1.0, self.n);
+    if let Some(out) = self.out {
+      instances[0][self.mode().out_row()] = out.0;
+    }
+    instances
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RangeInput {
+  pub range: usize,
+  /// The checked value. Only the prover needs it; keygen and verification
+  /// build the witness-free circuit without it.
+  #[serde(default)]
+  pub value: Option<FieldElement>,
+}
+
+impl RangeInput {
+  /// The circuit for `RANGE`, which must be the range the file asks for.
+  /// Without a value it is the circuit's `without_witnesses` shape.
+  pub fn circuit<const RANGE: usize>(&self) -> Result<RangeCircuit<Fp, RANGE>, InputError> {
+    if self.range != RANGE {
+      return Err(InputError::Invalid(format!("expected range {}, got {}", RANGE, self.range)));
+    }
+    Ok(match self.value {
+      Some(value) => RangeCircuit::new(value.0),
+      None => RangeCircuit::default(),
+    })
+  }
+
+  /// The range circuit has no public inputs.
+  pub fn instances(&self) -> Vec<Vec<Fp>> {
+    vec![]
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "circuit", rename_all = "lowercase")]
+pub enum CircuitInput {
+  Fibonacci(FibonacciInput),
+  Range(RangeInput),
+}
+
+impl CircuitInput {
+  pub fn from_json(json: &str) -> Result<Self, InputError> {
+    Ok(serde_json::from_str(json)?)
+  }
+
+  pub fn from_toml(text: &str) -> Result<Self, InputError> {
+    Ok(toml::from_str(text)?)
+  }
+
+  /// Reads a `.json` or `.toml` file.
+  pub fn from_path(path: impl AsRef<Path>) -> Result<Self, InputError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+      Some("json") => Self::from_json(&text),
+      Some("toml") => Self::from_toml(&text),
+      _ => Err(InputError::Invalid(format!("{}: expected a .json or .toml file", path.display()))),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use halo2_proofs::dev::MockProver;
+
+  use super::*;
+  use crate::{ fibonacci::OUT_ROW, range_check::range_check_native };
+
+  #[test]
+  fn parse_field_encodings() {
+    assert_eq!(parse_field("55").unwrap(), Fp::from(55));
+    assert_eq!(parse_field("0x37").unwrap(), Fp::from(55));
+    assert_eq!(parse_field("-1").unwrap(), -Fp::one());
+    assert_eq!(parse_field("-0x37").unwrap(), -Fp::from(55));
+    assert_eq!(parse_field("0").unwrap(), Fp::zero());
+    assert_eq!(parse_field(" 7 ").unwrap(), Fp::from(7));
+    assert_eq!(parse_field("18446744073709551616").unwrap(), Fp::from(u64::MAX) + Fp::one());
+
+    // p − 1 is the largest canonical value; p is rejected.
+    let p_minus_one = "28948022309329048855892746252171976963363056481941560715954676764349967630336";
+    let p = "28948022309329048855892746252171976963363056481941560715954676764349967630337";
+    assert_eq!(parse_field(p_minus_one).unwrap(), -Fp::one());
+    assert!(parse_field(p).is_err());
+    assert!(parse_field(&"9".repeat(100)).is_err());
+
+    for bad in ["", "-", "--1", "0x", "12a", "1.5", "0xg", "+1"] {
+      assert!(matches!(parse_field(bad), Err(InputError::Field(_))), "{:?}", bad);
+    }
+  }
+
+  #[test]
+  fn load_fibonacci() {
+    let toml = r#"
+      circuit = "fibonacci"
+      n = 9
+      f0 = 1
+      f1 = "0x1"
+    "#;
+    let CircuitInput::Fibonacci(input) = CircuitInput::from_toml(toml).unwrap() else {
+      panic!("expected a fibonacci input");
+    };
+    let circuit = input.circuit().unwrap();
+    let instances = input.instances();
+    assert_eq!(instances[0][OUT_ROW], Fp::from(55));
+    MockProver::run(circuit.k(), &circuit, instances).unwrap().assert_satisfied();
+
+    let json = r#"{ "circuit": "fibonacci", "n": 9, "private": true, "f0": "-1", "f1": 2, "out": "55" }"#;
+    let CircuitInput::Fibonacci(input) = CircuitInput::from_json(json).unwrap() else {
+      panic!("expected a fibonacci input");
+    };
+    assert_eq!(input.f0, FieldElement(-Fp::one()));
+    let circuit = input.circuit().unwrap();
+    let prover = MockProver::run(circuit.k(), &circuit, input.instances()).unwrap();
+    assert!(prover.verify().is_err());
+  }
+
+  #[test]
+  fn load_range() {
+    for (value, expected) in [("7", true), ("8", false), ("-1", false), ("0x0", true)] {
+      let json = format!(r#"{{ "circuit": "range", "range": 8, "value": "{}" }}"#, value);
+      let CircuitInput::Range(input) = CircuitInput::from_json(&json).unwrap() else {
+        panic!("expected a range input");
+      };
+      let circuit = input.circuit::<8>().unwrap();
+      let prover = MockProver::run(4, &circuit, input.instances()).unwrap();
+      assert_eq!(prover.verify().is_ok(), expected);
+      assert_eq!(range_check_native(input.value.unwrap().0, 8), expected);
+    }
+
+    let CircuitInput::Range(input) = CircuitInput::from_toml("circuit = \"range\"\nrange = 8\nvalue = -3").unwrap() else {
+      panic!("expected a range input");
+    };
+    assert_eq!(input.value, Some(FieldElement(-Fp::from(3))));
+    assert!(matches!(input.circuit::<16>(), Err(InputError::Invalid(_))));
+  }
+
+  #[test]
+  fn load_errors() {
+    assert!(matches!(CircuitInput::from_json("{"), Err(InputError::Json(_))));
+    assert!(matches!(CircuitInput::from_json(r#"{ "circuit": "sudoku" }"#), Err(InputError::Json(_))));
+    assert!(matches!(CircuitInput::from_json(r#"{ "circuit": "range", "value": 1 }"#), Err(InputError::Json(_))));
+    // Verifiers leave the value out.
+    assert!(matches!(
+      CircuitInput::from_json(r#"{ "circuit": "range", "range": 8 }"#),
+      Ok(CircuitInput::Range(RangeInput { value: None, .. }))
+    ));
+    assert!(matches!(
+      CircuitInput::from_json(r#"{ "circuit": "range", "range": 8, "value": "0xzz" }"#),
+      Err(InputError::Json(_))
+    ));
+    assert!(matches!(
+      CircuitInput::from_json(r#"{ "circuit": "range", "range": 8, "value": 1, "extra": 1 }"#),
+      Err(InputError::Json(_))
+    ));
+    assert!(matches!(CircuitInput::from_toml("circuit = \"fibonacci\"\nn = \"nine\""), Err(InputError::Toml(_))));
+
+    let input = FibonacciInput { n: 1, f0: one(), f1: one(), private: false, out: None };
+    assert!(matches!(input.circuit(), Err(InputError::Invalid(_))));
+
+    assert!(matches!(CircuitInput::from_path("/nonexistent/input.json"), Err(InputError::Io(_))));
+  }
+}
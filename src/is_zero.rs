@@ -0,0 +1,346 @@
+use halo2_proofs::{
+  plonk::{ Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector },
+  circuit::*,
+  poly::Rotation,
+};
+
+use group::ff::PrimeField;
+
+use std::marker::PhantomData;
+
+/// Outputs whether `v` is zero. The prover witnesses `inv`, meant to be
+/// `v⁻¹` (or anything when `v = 0`), and the gate enforces
+///
+///   `out = 1 − v · inv` and `v · out = 0`.
+///
+/// For `v ≠ 0` the second constraint forces `out = 0`; for `v = 0` the
+/// first forces `out = 1`. A wrong `inv` breaks one of the two, so the
+/// output cannot be chosen freely.
+#[derive(Clone, Debug)]
+pub struct IsZeroConfig {
+  value: Column<Advice>,
+  inverse: Column<Advice>,
+  output: Column<Advice>,
+  q_is_zero: Selector,
+}
+
+#[derive(Debug, Clone)]
+pub struct IsZeroChip<F: PrimeField> {
+  config: IsZeroConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> IsZeroChip<F> {
+  pub fn construct(config: IsZeroConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>) -> IsZeroConfig {
+    let value = meta.advice_column();
+    let inverse = meta.advice_column();
+    let output = meta.advice_column();
+    let q_is_zero = meta.selector();
+
+    meta.enable_equality(value);
+    meta.enable_equality(output);
+
+    meta.create_gate("is zero", |meta| {
+      let q = meta.query_selector(q_is_zero);
+      let value = meta.query_advice(value, Rotation::cur());
+      let inverse = meta.query_advice(inverse, Rotation::cur());
+      let output = meta.query_advice(output, Rotation::cur());
+      let one = Expression::Constant(F::ONE);
+
+      Constraints::with_selector(q, [
+        ("out = 1 - value * inv", output.clone() - (one - value.clone() * inverse)),
+        ("value * out = 0", value * output),
+      ])
+    });
+
+    IsZeroConfig { value, inverse, output, q_is_zero }
+  }
+
+  /// Witnesses `value` and returns a cell holding 1 if it is zero and 0
+  /// otherwise.
+  pub fn assign(&self, mut layouter: impl Layouter<F>, value: Value<F>) -> Result<AssignedCell<F, F>, Error> {
+    layouter.assign_region(
+      || "is zero",
+      |mut region| {
+        region.assign_advice(|| "value", self.config.value, 0, || value)?;
+        self.assign_output(&mut region, 0, value)
+      }
+    )
+  }
+
+  /// Like [`Self::assign`] for a cell assigned by another gadget, which is
+  /// copied in under an equality constraint.
+  pub fn assign_cell(&self, mut layouter: impl Layouter<F>, cell: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+    layouter.assign_region(
+      || "is zero",
+      |mut region| {
+        let value = cell.copy_advice(|| "value", &mut region, self.config.value, 0)?;
+        self.assign_output(&mut region, 0, value.value().copied())
+      }
+    )
+  }
+
+  /// Enables the gate at `offset`, where the value column already holds
+  /// `value`, and witnesses its inverse and the output.
+  fn assign_output(&self, region: &mut Region<'_, F>, offset: usize, value: Value<F>) -> Result<AssignedCell<F, F>, Error> {
+    let inverse = value.map(|v| v.invert().unwrap_or(F::ZERO));
+    let output = value.map(|v| if v.is_zero_vartime() { F::ONE } else { F::ZERO });
+    self.assign_witnesses(region, offset, inverse, output)
+  }
+
+  fn assign_witnesses(
+    &self,
+    region: &mut Region<'_, F>,
+    offset: usize,
+    inverse: Value<F>,
+    output: Value<F>
+  ) -> Result<AssignedCell<F, F>, Error> {
+    self.config.q_is_zero.enable(region, offset)?;
+    region.assign_advice(|| "inverse", self.config.inverse, offset, || inverse)?;
+    region.assign_advice(|| "is zero", self.config.output, offset, || output)
+  }
+}
+
+/// Outputs whether `a = b`, by feeding `a − b` into an [`IsZeroChip`] on the
+/// same row.
+#[derive(Clone, Debug)]
+pub struct IsEqualConfig {
+  a: Column<Advice>,
+  b: Column<Advice>,
+  q_diff: Selector,
+  is_zero: IsZeroConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct IsEqualChip<F: PrimeField> {
+  config: IsEqualConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> IsEqualChip<F> {
+  pub fn construct(config: IsEqualConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>) -> IsEqualConfig {
+    let a = meta.advice_column();
+    let b = meta.advice_column();
+    let q_diff = meta.selector();
+    let is_zero = IsZeroChip::configure(meta);
+
+    meta.enable_equality(a);
+    meta.enable_equality(b);
+
+    meta.create_gate("is equal difference", |meta| {
+      let q = meta.query_selector(q_diff);
+      let a = meta.query_advice(a, Rotation::cur());
+      let b = meta.query_advice(b, Rotation::cur());
+      let diff = meta.query_advice(is_zero.value, Rotation::cur());
+
+      Constraints::with_selector(q, [("diff = a - b", diff - (a - b))])
+    });
+
+    IsEqualConfig { a, b, q_diff, is_zero }
+  }
+
+  /// Witnesses `a` and `b` and returns a cell holding 1 if they are equal
+  /// and 0 otherwise.
+  pub fn assign(&self, mut layouter: impl Layouter<F>, a: Value<F>, b: Value<F>) -> Result<AssignedCell<F, F>, Error> {
+    layouter.assign_region(
+      || "is equal",
+      |mut region| {
+        region.assign_advice(|| "a", self.config.a, 0, || a)?;
+        region.assign_advice(|| "b", self.config.b, 0, || b)?;
+        self.assign_output(&mut region, a - b)
+      }
+    )
+  }
+
+  /// Like [`Self::assign`] for cells assigned by other gadgets.
+  pub fn assign_cells(
+    &self,
+    mut layouter: impl Layouter<F>,
+    a: &AssignedCell<F, F>,
+    b: &AssignedCell<F, F>
+  ) -> Result<AssignedCell<F, F>, Error> {
+    layouter.assign_region(
+      || "is equal",
+      |mut region| {
+        let a = a.copy_advice(|| "a", &mut region, self.config.a, 0)?;
+        let b = b.copy_advice(|| "b", &mut region, self.config.b, 0)?;
+        self.assign_output(&mut region, a.value().copied() - b.value())
+      }
+    )
+  }
+
+  fn assign_output(&self, region: &mut Region<'_, F>, diff: Value<F>) -> Result<AssignedCell<F, F>, Error> {
+    self.config.q_diff.enable(region, 0)?;
+    region.assign_advice(|| "a - b", self.config.is_zero.value, 0, || diff)?;
+    IsZeroChip::construct(self.config.is_zero.clone()).assign_output(region, 0, diff)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::range_check::{ RangeChip, RangeConfig };
+  use group::ff::Field;
+  use halo2_proofs::{
+    dev::{ FailureLocation, MockProver, VerifyFailure },
+    pasta::Fp,
+    plonk::{ Any, Circuit, Instance },
+  };
+
+  /// Checks whether `value` is zero and exposes the output on instance
+  /// row 0. With `inverse` set, that inverse and the `claimed` output are
+  /// witnessed instead of the honest ones.
+  #[derive(Default)]
+  struct IsZeroCircuit {
+    value: Value<Fp>,
+    inverse: Option<Value<Fp>>,
+    claimed: Value<Fp>,
+  }
+
+  impl Circuit<Fp> for IsZeroCircuit {
+    type Config = (IsZeroConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+      Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+      let instance = meta.instance_column();
+      meta.enable_equality(instance);
+      (IsZeroChip::configure(meta), instance)
+    }
+
+    fn synthesize(&self, (config, instance): Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+      let chip = IsZeroChip::construct(config.clone());
+      let output = match self.inverse {
+        None => chip.assign(layouter.namespace(|| "is zero"), self.value)?,
+        Some(inverse) =>
+          layouter.assign_region(
+            || "malicious is zero",
+            |mut region| {
+              region.assign_advice(|| "value", config.value, 0, || self.value)?;
+              chip.assign_witnesses(&mut region, 0, inverse, self.claimed)
+            }
+          )?,
+      };
+      layouter.constrain_instance(output.cell(), instance, 0)
+    }
+  }
+
+  /// Runs [`IsZeroCircuit`] with `claimed` as the public output.
+  fn is_zero(value: Fp, inverse: Option<Fp>, claimed: Fp) -> Result<(), Vec<VerifyFailure>> {
+    let circuit = IsZeroCircuit {
+      value: Value::known(value),
+      inverse: inverse.map(Value::known),
+      claimed: Value::known(claimed),
+    };
+    MockProver::run(4, &circuit, vec![vec![claimed]]).unwrap().verify()
+  }
+
+  #[test]
+  fn is_zero_honest() {
+    for value in [Fp::zero(), Fp::one(), -Fp::one(), Fp::from(1 << 40)] {
+      let expected = if value == Fp::zero() { Fp::one() } else { Fp::zero() };
+      assert_eq!(is_zero(value, None, expected), Ok(()));
+      assert!(is_zero(value, None, Fp::one() - expected).is_err());
+    }
+  }
+
+  #[test]
+  fn is_zero_malicious_inverse() {
+    let value = Fp::from(5);
+    let honest = value.invert().unwrap();
+    assert_eq!(is_zero(value, Some(honest), Fp::zero()), Ok(()));
+
+    // Claiming 5 is zero: any inverse with `1 - 5 · inv = 1` is inv = 0, and
+    // then `5 · 1 ≠ 0`.
+    let failures = is_zero(value, Some(Fp::zero()), Fp::one()).unwrap_err();
+    assert_eq!(failures, vec![VerifyFailure::ConstraintNotSatisfied {
+      constraint: ((0, "is zero").into(), 1, "value * out = 0").into(),
+      location: FailureLocation::InRegion { region: (0, "malicious is zero").into(), offset: 0 },
+      cell_values: vec![
+        (((Any::Advice, 0).into(), 0).into(), "0x5".to_string()),
+        (((Any::Advice, 2).into(), 0).into(), "1".to_string()),
+      ],
+    }]);
+
+    // A wrong inverse with the honest output breaks the first constraint.
+    for inverse in [Fp::zero(), Fp::one(), honest + Fp::one()] {
+      assert!(is_zero(value, Some(inverse), Fp::zero()).is_err());
+    }
+
+    // Zero has no inverse, so `out = 1 - 0 · inv = 1` whatever is witnessed.
+    for inverse in [Fp::zero(), Fp::from(7)] {
+      assert_eq!(is_zero(Fp::zero(), Some(inverse), Fp::one()), Ok(()));
+      assert!(is_zero(Fp::zero(), Some(inverse), Fp::zero()).is_err());
+    }
+  }
+
+  /// Compares `a` and `b`, range checks the output as a boolean with a
+  /// `RangeChip<2>` and compares it with that copy, to show the output
+  /// composes with other gadgets. Exposes `a = b` and the second comparison.
+  #[derive(Default)]
+  struct IsEqualCircuit {
+    a: Value<Fp>,
+    b: Value<Fp>,
+  }
+
+  impl Circuit<Fp> for IsEqualCircuit {
+    type Config = (IsEqualConfig, RangeConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+      Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+      let instance = meta.instance_column();
+      meta.enable_equality(instance);
+      (IsEqualChip::configure(meta), RangeChip::<Fp, 2>::configure(meta), instance)
+    }
+
+    fn synthesize(&self, (config, range, instance): Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+      let chip = IsEqualChip::construct(config);
+      let range = RangeChip::<Fp, 2>::construct(range);
+
+      let equal = chip.assign(layouter.namespace(|| "a = b"), self.a, self.b)?;
+      let boolean = range.check_cell(layouter.namespace(|| "boolean"), &equal)?;
+      let same = chip.assign_cells(layouter.namespace(|| "copy = out"), &boolean, &equal)?;
+
+      layouter.constrain_instance(equal.cell(), instance, 0)?;
+      layouter.constrain_instance(same.cell(), instance, 1)
+    }
+  }
+
+  #[test]
+  fn is_equal_matches_native() {
+    let values = [Fp::zero(), Fp::one(), Fp::from(7), -Fp::one()];
+    for a in values {
+      for b in values {
+        let circuit = IsEqualCircuit { a: Value::known(a), b: Value::known(b) };
+        let expected = if a == b { Fp::one() } else { Fp::zero() };
+
+        let prover = MockProver::run(5, &circuit, vec![vec![expected, Fp::one()]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let prover = MockProver::run(5, &circuit, vec![vec![Fp::one() - expected, Fp::one()]]).unwrap();
+        assert!(prover.verify().is_err());
+      }
+    }
+  }
+}
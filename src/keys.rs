@@ -0,0 +1,294 @@
+use std::{ fmt, fs, io::{ self, Write }, path::{ Path, PathBuf } };
+
+use halo2_proofs::{
+  pasta::{ EqAffine, Fp },
+  plonk::{ keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error, ProvingKey, VerifyingKey },
+  poly::commitment::Params,
+};
+
+/// A 32-byte Blake2b digest.
+pub type Fingerprint = [u8; 32];
+
+#[derive(Debug)]
+pub enum KeyError {
+  Io(io::Error),
+  Plonk(Error),
+  /// The fingerprints recorded at `path` belong to a different circuit shape.
+  Mismatch { path: PathBuf },
+  /// A parameter or fingerprint file that could not be parsed.
+  Corrupt { path: PathBuf },
+}
+
+impl fmt::Display for KeyError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      KeyError::Io(err) => write!(f, "key store I/O error: {}", err),
+      KeyError::Plonk(err) => write!(f, "key generation failed: {:?}", err),
+      KeyError::Mismatch { path } =>
+        write!(
+          f,
+          "{} was generated for a different circuit shape; delete it to regenerate the keys",
+          path.display()
+        ),
+      KeyError::Corrupt { path } => write!(f, "{} is corrupt; delete it to regenerate", path.display()),
+    }
+  }
+}
+
+impl std::error::Error for KeyError {}
+
+impl From<io::Error> for KeyError {
+  fn from(err: io::Error) -> Self {
+    KeyError::Io(err)
+  }
+}
+
+impl From<Error> for KeyError {
+  fn from(err: Error) -> Self {
+    KeyError::Plonk(err)
+  }
+}
+
+fn blake2b(data: &[u8]) -> Fingerprint {
+  let hash = blake2b_simd::Params::new().hash_length(32).hash(data);
+  let mut out = [0u8; 32];
+  out.copy_from_slice(hash.as_bytes());
+  out
+}
+
+/// Hash of the circuit's pinned `ConstraintSystem`: columns, gates, lookups
+/// and the permutation, but not fixed column values.
+pub fn shape_fingerprint<C: Circuit<Fp>>() -> Fingerprint {
+  let mut meta = ConstraintSystem::<Fp>::default();
+  C::configure(&mut meta);
+  blake2b(format!("{:?}", meta.pinned()).as_bytes())
+}
+
+/// Hash of the pinned verifying key, which also covers the domain, the
+/// fixed commitments and the permutation commitments.
+pub fn vk_fingerprint(vk: &VerifyingKey<EqAffine>) -> Fingerprint {
+  blake2b(format!("{:?}", vk.pinned()).as_bytes())
+}
+
+/// Lowercase hex, as fingerprints are written in key files.
+pub fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Inverse of [`to_hex`], accepting either case.
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+  if !hex.len().is_multiple_of(2) {
+    return None;
+  }
+  (0..hex.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+    .collect()
+}
+
+fn fingerprint_from_hex(hex: &str) -> Option<Fingerprint> {
+  from_hex(hex)?.try_into().ok()
+}
+
+/// Caches IPA parameters in a directory and records key fingerprints next
+/// to them.
+///
+/// Keys themselves are never stored: `halo2_proofs` 0.3 can serialize
+/// `Params` but not the keys, so every run regenerates them from the cached
+/// parameters, which is the cheap part for large `k`. Each circuit gets a
+/// `<name>-k<k>.vk` file recording the fingerprints of its constraint
+/// system and verifying key. A regenerated key must match them, so a
+/// circuit whose shape changed under the same name is reported instead of
+/// silently producing keys that old proofs do not verify under.
+#[derive(Clone, Debug)]
+pub struct KeyStore {
+  dir: PathBuf,
+}
+
+impl KeyStore {
+  pub fn new(dir: impl Into<PathBuf>) -> Self {
+    Self { dir: dir.into() }
+  }
+
+  pub fn dir(&self) -> &Path {
+    &self.dir
+  }
+
+  /// Parameters only depend on `k`, so circuits share them.
+  pub fn params_path(&self, k: u32) -> PathBuf {
+    self.dir.join(format!("params-k{}.bin", k))
+  }
+
+  pub fn vk_path(&self, name: &str, k: u32) -> PathBuf {
+    self.dir.join(format!("{}-k{}.vk", name, k))
+  }
+
+  /// Reads the parameters for `k`, generating and writing them on a miss.
+  /// They are written to a temporary file and renamed into place, so an
+  /// interrupted write leaves no partial file behind.
+  pub fn params(&self, k: u32) -> Result<Params<EqAffine>, KeyError> {
+    let path = self.params_path(k);
+    if path.exists() {
+      let params = match Params::read(&mut io::BufReader::new(fs::File::open(&path)?)) {
+        Ok(params) => params,
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Err(KeyError::Corrupt { path }),
+        Err(err) => return Err(err.into()),
+      };
+      if params.get_g().len() != 1 << k {
+        return Err(KeyError::Corrupt { path });
+      }
+      return Ok(params);
+    }
+
+    let params = Params::new(k);
+    fs::create_dir_all(&self.dir)?;
+    let partial = path.with_extension("bin.tmp");
+    let mut file = io::BufWriter::new(fs::File::create(&partial)?);
+    params.write(&mut file)?;
+    file.flush()?;
+    drop(file);
+    fs::rename(&partial, &path)?;
+    Ok(params)
+  }
+
+  /// The verifying key for `circuit` registered as `name`, checked against
+  /// the fingerprints recorded on a previous run.
+  pub fn verifying_key<C: Circuit<Fp>>(
+    &self,
+    name: &str,
+    k: u32,
+    params: &Params<EqAffine>,
+    circuit: &C
+  ) -> Result<VerifyingKey<EqAffine>, KeyError> {
+    let path = self.vk_path(name, k);
+    let shape = shape_fingerprint::<C>();
+
+    let cached = if path.exists() { Some(self.read_fingerprints(&path)?) } else { None };
+    if let Some((cached_shape, _)) = cached {
+      if cached_shape != shape {
+        return Err(KeyError::Mismatch { path });
+      }
+    }
+
+    let vk = keygen_vk(params, &circuit.without_witnesses())?;
+    let fingerprint = vk_fingerprint(&vk);
+
+    match cached {
+      Some((_, cached_vk)) if cached_vk != fingerprint => Err(KeyError::Mismatch { path }),
+      Some(_) => Ok(vk),
+      None => {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, format!("shape {}\nvk {}\n", to_hex(&shape), to_hex(&fingerprint)))?;
+        Ok(vk)
+      }
+    }
+  }
+
+  /// Parameters and proving key for `circuit`, as [`crate::prover::setup`]
+  /// returns them. The parameters come from the cache; the keys are
+  /// regenerated and checked against the recorded fingerprints.
+  pub fn setup<C: Circuit<Fp>>(
+    &self,
+    name: &str,
+    k: u32,
+    circuit: &C
+  ) -> Result<(Params<EqAffine>, ProvingKey<EqAffine>), KeyError> {
+    let params = self.params(k)?;
+    let vk = self.verifying_key(name, k, &params, circuit)?;
+    let pk = keygen_pk(&params, vk, &circuit.without_witnesses())?;
+    Ok((params, pk))
+  }
+
+  fn read_fingerprints(&self, path: &Path) -> Result<(Fingerprint, Fingerprint), KeyError> {
+    let contents = fs::read_to_string(path)?;
+    let mut lines = contents.lines().map(|line| line.split_once(' '));
+    let corrupt = || KeyError::Corrupt { path: path.to_path_buf() };
+
+    let shape = match lines.next() {
+      Some(Some(("shape", hex))) => fingerprint_from_hex(hex).ok_or_else(corrupt)?,
+      _ => return Err(corrupt()),
+    };
+    let vk = match lines.next() {
+      Some(Some(("vk", hex))) => fingerprint_from_hex(hex).ok_or_else(corrupt)?,
+      _ => return Err(corrupt()),
+    };
+    Ok((shape, vk))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fibonacci::FiboCircuit,
+    fibonacci_rotation::FiboRotationCircuit,
+    prover::{ prove, verify },
+  };
+
+  fn store(test: &str) -> KeyStore {
+    let dir = std::env::temp_dir().join(format!("halo2-keys-{}-{}", std::process::id(), test));
+    let _ = fs::remove_dir_all(&dir);
+    KeyStore::new(dir)
+  }
+
+  #[test]
+  fn key_store_round_trip() {
+    let store = store("round-trip");
+    let circuit = FiboCircuit::<Fp>::new(9);
+    let k = circuit.k();
+
+    let (params, pk) = store.setup("fibonacci", k, &circuit).unwrap();
+    assert!(store.params_path(k).exists());
+    assert!(!store.params_path(k).with_extension("bin.tmp").exists());
+    assert!(store.vk_path("fibonacci", k).exists());
+
+    // A second run reads the parameters back and accepts the regenerated key.
+    let (cached_params, cached_pk) = store.setup("fibonacci", k, &circuit).unwrap();
+    let (mut written, mut reread) = (vec![], vec![]);
+    params.write(&mut written).unwrap();
+    cached_params.write(&mut reread).unwrap();
+    assert_eq!(written, reread);
+    assert_eq!(vk_fingerprint(pk.get_vk()), vk_fingerprint(cached_pk.get_vk()));
+
+    // Proofs made on the first run verify under the second run's keys.
+    let instances = circuit.instances(Fp::one(), Fp::one());
+    let proof = prove(&params, &pk, circuit, &instances).unwrap();
+    assert!(verify(&cached_params, cached_pk.get_vk(), &proof, &instances));
+
+    fs::remove_dir_all(store.dir()).unwrap();
+  }
+
+  #[test]
+  fn key_store_shape_mismatch() {
+    let store = store("mismatch");
+    let circuit = FiboCircuit::<Fp>::new(9);
+    let rotation = FiboRotationCircuit::<Fp>::new(9);
+    let k = circuit.k().max(rotation.k());
+    store.setup("fibonacci", k, &circuit).unwrap();
+
+    // Another layout registered under the same name is rejected.
+    let err = store.setup("fibonacci", k, &rotation).unwrap_err();
+    assert!(matches!(err, KeyError::Mismatch { .. }));
+    assert!(err.to_string().contains("regenerate"));
+
+    // Same shape but a different length changes the fixed columns.
+    let longer = FiboCircuit::<Fp>::new(10);
+    let err = store.setup("fibonacci", k, &longer).unwrap_err();
+    assert!(matches!(err, KeyError::Mismatch { .. }));
+
+    // After deleting the stale file the key is regenerated.
+    fs::remove_file(store.vk_path("fibonacci", k)).unwrap();
+    store.setup("fibonacci", k, &rotation).unwrap();
+
+    fs::write(store.vk_path("fibonacci", k), "not a key").unwrap();
+    let err = store.setup("fibonacci", k, &rotation).unwrap_err();
+    assert!(matches!(err, KeyError::Corrupt { .. }));
+
+    // Truncated parameters are reported as corrupt, not as an I/O error.
+    let params = fs::read(store.params_path(k)).unwrap();
+    fs::write(store.params_path(k), &params[..params.len() / 2]).unwrap();
+    assert!(matches!(store.params(k), Err(KeyError::Corrupt { .. })));
+
+    fs::remove_dir_all(store.dir()).unwrap();
+  }
+}
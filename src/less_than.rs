@@ -0,0 +1,464 @@
+use halo2_proofs::{
+  plonk::{ Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector },
+  circuit::*,
+  poly::Rotation,
+};
+
+use group::ff::PrimeField;
+
+use std::marker::PhantomData;
+
+use crate::range_check_decompose::{ DecomposeChip, DecomposeConfig };
+
+/// Compares `N`-bit values, with `N` fixed at configure time. A comparison
+/// row holds `a`, `b`, a witnessed flag `lt` and
+///
+///   `low = a − b + lt · 2^N`,
+///
+/// with `lt` boolean and `low` range checked to `N` bits by a
+/// [`DecomposeChip`]. For `a, b < 2^N` only `lt = [a < b]` puts `low` in
+/// range: `a − b` is negative exactly when `a < b`, and adding `2^N` lifts
+/// it back into `[0, 2^N)`. `a ≤ b` is `a < b + 1`, so `lte` rows subtract
+/// one more. `max` and `min` select between `a` and `b` on the same row.
+///
+/// The inputs are [`CheckedCell`]s, range checked once when
+/// [`LtChip::assign`] or [`LtChip::check`] makes them, so a comparison only
+/// decomposes `low`. An unchecked `a = 2^N` would let `low` land in range
+/// with either flag.
+///
+/// The flag is witnessed on its own rather than read off as bit `N` of a
+/// decomposition of `a − b + 2^N`. The `N`-bit check on `low` already
+/// rules out the wrong flag, since the two candidates for `low` differ by
+/// exactly `2^N`, and the boolean constraint costs one degree-2 term where
+/// the top bit would need an `N + 1`-bit decomposition with its last word
+/// split out.
+#[derive(Clone, Debug)]
+pub struct LtConfig {
+  a: Column<Advice>,
+  b: Column<Advice>,
+  low: Column<Advice>,
+  lt: Column<Advice>,
+  out: Column<Advice>,
+  q_lt: Selector,
+  q_lte: Selector,
+  q_max: Selector,
+  q_min: Selector,
+  num_bits: usize,
+  decompose: DecomposeConfig,
+}
+
+impl LtConfig {
+  /// Bit width of the compared values.
+  pub fn num_bits(&self) -> usize {
+    self.num_bits
+  }
+}
+
+/// A cell [`LtChip`] has range checked to `num_bits` bits. Only the chip
+/// makes these, so comparisons can rely on the bound.
+#[derive(Clone, Debug)]
+pub struct CheckedCell<F: PrimeField> {
+  cell: AssignedCell<F, F>,
+  num_bits: usize,
+}
+
+impl<F: PrimeField> CheckedCell<F> {
+  pub fn cell(&self) -> &AssignedCell<F, F> {
+    &self.cell
+  }
+
+  /// Bits the value was checked to.
+  pub fn num_bits(&self) -> usize {
+    self.num_bits
+  }
+}
+
+/// What a comparison row constrains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+  Lt,
+  Lte,
+  Max,
+  Min,
+}
+
+#[derive(Debug, Clone)]
+pub struct LtChip<F: PrimeField, const K: usize> {
+  config: LtConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField, const K: usize> LtChip<F, K> {
+  pub fn construct(config: LtConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>, num_bits: usize) -> LtConfig {
+    assert!(num_bits + 1 < F::NUM_BITS as usize, "a − b + 2^N must not wrap the field");
+
+    let [a, b, low, lt, out] = [(); 5].map(|_| meta.advice_column());
+    let q_lt = meta.selector();
+    let q_lte = meta.selector();
+    let q_max = meta.selector();
+    let q_min = meta.selector();
+
+    for column in [a, b, lt, out] {
+      meta.enable_equality(column);
+    }
+
+    let two_pow_n = Expression::Constant(F::from(2).pow_vartime([num_bits as u64]));
+    let one = Expression::Constant(F::ONE);
+
+    for (name, selector, offset) in [("a < b", q_lt, F::ZERO), ("a <= b", q_lte, F::ONE)] {
+      meta.create_gate(name, |meta| {
+        let q = meta.query_selector(selector);
+        let a = meta.query_advice(a, Rotation::cur());
+        let b = meta.query_advice(b, Rotation::cur());
+        let low = meta.query_advice(low, Rotation::cur());
+        let lt = meta.query_advice(lt, Rotation::cur());
+        let offset = Expression::Constant(offset);
+
+        Constraints::with_selector(q, [
+          ("low = a - b + lt * 2^N", low - (a - b - offset + lt.clone() * two_pow_n.clone())),
+          ("lt is boolean", lt.clone() * (one.clone() - lt)),
+        ])
+      });
+    }
+
+    // `max` picks `b` when `a < b`, `min` picks `a`.
+    for (name, selector, lt_picks_b) in [("max", q_max, true), ("min", q_min, false)] {
+      meta.create_gate(name, |meta| {
+        let q = meta.query_selector(selector);
+        let a = meta.query_advice(a, Rotation::cur());
+        let b = meta.query_advice(b, Rotation::cur());
+        let lt = meta.query_advice(lt, Rotation::cur());
+        let out = meta.query_advice(out, Rotation::cur());
+        let (picked, other) = if lt_picks_b { (b, a) } else { (a, b) };
+
+        Constraints::with_selector(q, [("out = lt ? picked : other", out - (other.clone() + lt * (picked - other)))])
+      });
+    }
+
+    // `low` is copied into the running sum anyway, so it can be the running
+    // sum column. Strict checks fix the last word to zero with a constant.
+    let decompose = DecomposeChip::<F, K>::configure_with_column(meta, low);
+    let constant = meta.fixed_column();
+    meta.enable_constant(constant);
+
+    LtConfig { a, b, low, lt, out, q_lt, q_lte, q_max, q_min, num_bits, decompose }
+  }
+
+  /// Loads the decomposition word table. Call once per circuit.
+  pub fn load(&self, layouter: impl Layouter<F>) -> Result<(), Error> {
+    self.decompose().load(layouter)
+  }
+
+  /// Witnesses `value` and checks it fits in `N` bits, so it can be
+  /// compared.
+  pub fn assign(&self, mut layouter: impl Layouter<F>, value: Value<F>) -> Result<CheckedCell<F>, Error> {
+    let zs = self.decompose().witness_check(layouter.namespace(|| "input"), value, self.config.num_bits, true)?;
+    Ok(CheckedCell { cell: zs[0].clone(), num_bits: self.config.num_bits })
+  }
+
+  /// Checks a cell from another chip fits in `N` bits, so it can be
+  /// compared.
+  pub fn check(&self, mut layouter: impl Layouter<F>, cell: &AssignedCell<F, F>) -> Result<CheckedCell<F>, Error> {
+    self.decompose().copy_check(layouter.namespace(|| "input"), cell, self.config.num_bits, true)?;
+    Ok(CheckedCell { cell: cell.clone(), num_bits: self.config.num_bits })
+  }
+
+  /// A cell holding 1 if `a < b` and 0 otherwise.
+  pub fn lt(&self, layouter: impl Layouter<F>, a: &CheckedCell<F>, b: &CheckedCell<F>) -> Result<AssignedCell<F, F>, Error> {
+    self.compare(layouter, a, b, Comparison::Lt)
+  }
+
+  /// A cell holding 1 if `a <= b` and 0 otherwise.
+  pub fn lte(&self, layouter: impl Layouter<F>, a: &CheckedCell<F>, b: &CheckedCell<F>) -> Result<AssignedCell<F, F>, Error> {
+    self.compare(layouter, a, b, Comparison::Lte)
+  }
+
+  /// A cell holding 1 if `a > b` and 0 otherwise.
+  pub fn gt(&self, layouter: impl Layouter<F>, a: &CheckedCell<F>, b: &CheckedCell<F>) -> Result<AssignedCell<F, F>, Error> {
+    self.compare(layouter, b, a, Comparison::Lt)
+  }
+
+  /// The larger of `a` and `b`. It is one of them, so it stays checked.
+  pub fn max(&self, layouter: impl Layouter<F>, a: &CheckedCell<F>, b: &CheckedCell<F>) -> Result<CheckedCell<F>, Error> {
+    let cell = self.compare(layouter, a, b, Comparison::Max)?;
+    Ok(CheckedCell { cell, num_bits: a.num_bits.max(b.num_bits) })
+  }
+
+  /// The smaller of `a` and `b`. It is one of them, so it stays checked.
+  pub fn min(&self, layouter: impl Layouter<F>, a: &CheckedCell<F>, b: &CheckedCell<F>) -> Result<CheckedCell<F>, Error> {
+    let cell = self.compare(layouter, a, b, Comparison::Min)?;
+    Ok(CheckedCell { cell, num_bits: a.num_bits.max(b.num_bits) })
+  }
+
+  fn decompose(&self) -> DecomposeChip<F, K> {
+    DecomposeChip::construct(self.config.decompose.clone())
+  }
+
+  /// Lays out one comparison row and range checks its `low` cell. Returns
+  /// the flag for `Lt` and `Lte`, and the selected input for `Max` and
+  /// `Min`.
+  fn compare(
+    &self,
+    mut layouter: impl Layouter<F>,
+    a: &CheckedCell<F>,
+    b: &CheckedCell<F>,
+    comparison: Comparison
+  ) -> Result<AssignedCell<F, F>, Error> {
+    let config = &self.config;
+    assert!(
+      a.num_bits <= config.num_bits && b.num_bits <= config.num_bits,
+      "compared cells must be checked to at most N bits"
+    );
+    let two_pow_n = F::from(2).pow_vartime([config.num_bits as u64]);
+
+    let (low, result) = layouter.assign_region(
+      || "compare",
+      |mut region| {
+        let a = a.cell.copy_advice(|| "a", &mut region, config.a, 0)?;
+        let b = b.cell.copy_advice(|| "b", &mut region, config.b, 0)?;
+
+        let offset = match comparison {
+          Comparison::Lte => F::ONE,
+          _ => F::ZERO,
+        };
+        let flag = a
+          .value()
+          .zip(b.value())
+          .map(|(a, b)| {
+            let (a, b) = (a.to_repr(), b.to_repr());
+            // Little-endian representations, compared from the top byte.
+            let less = a.as_ref().iter().rev().cmp(b.as_ref().iter().rev()).is_lt();
+            let equal = a.as_ref() == b.as_ref();
+            if less || (comparison == Comparison::Lte && equal) { F::ONE } else { F::ZERO }
+          });
+        let low = a.value().zip(b.value()).zip(flag).map(|((a, b), flag)| *a - b - offset + flag * two_pow_n);
+
+        let selector = if comparison == Comparison::Lte { config.q_lte } else { config.q_lt };
+        selector.enable(&mut region, 0)?;
+        let low = region.assign_advice(|| "low", config.low, 0, || low)?;
+        let flag = region.assign_advice(|| "lt", config.lt, 0, || flag)?;
+
+        let result = match comparison {
+          Comparison::Lt | Comparison::Lte => flag,
+          Comparison::Max | Comparison::Min => {
+            let q_select = if comparison == Comparison::Max { config.q_max } else { config.q_min };
+            q_select.enable(&mut region, 0)?;
+
+            let picks_b = comparison == Comparison::Max;
+            let out = a
+              .value()
+              .zip(b.value())
+              .zip(flag.value())
+              .map(|((a, b), lt)| if (*lt == F::ONE) == picks_b { *b } else { *a });
+            region.assign_advice(|| "selected", config.out, 0, || out)?
+          }
+        };
+
+        Ok((low, result))
+      }
+    )?;
+
+    self.decompose().copy_check(layouter.namespace(|| "low"), &low, config.num_bits, true)?;
+    Ok(result)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use halo2_proofs::{ dev::MockProver, pasta::Fp, plonk::{ Circuit, Instance } };
+
+  /// Compares each pair with every helper and exposes the results in
+  /// order: `lt, lte, gt, max, min`.
+  #[derive(Default)]
+  struct CompareCircuit<const NUM_BITS: usize> {
+    pairs: Vec<(Value<Fp>, Value<Fp>)>,
+  }
+
+  impl<const NUM_BITS: usize> Circuit<Fp> for CompareCircuit<NUM_BITS> {
+    type Config = (LtConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+      Self { pairs: vec![(Value::unknown(), Value::unknown()); self.pairs.len()] }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+      let instance = meta.instance_column();
+      meta.enable_equality(instance);
+      (LtChip::<Fp, 8>::configure(meta, NUM_BITS), instance)
+    }
+
+    fn synthesize(&self, (config, instance): Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+      let chip = LtChip::<Fp, 8>::construct(config);
+      chip.load(layouter.namespace(|| "word table"))?;
+
+      let mut row = 0;
+      for &(a, b) in &self.pairs {
+        let a = chip.assign(layouter.namespace(|| "a"), a)?;
+        let b = chip.assign(layouter.namespace(|| "b"), b)?;
+        let results = [
+          chip.lt(layouter.namespace(|| "lt"), &a, &b)?,
+          chip.lte(layouter.namespace(|| "lte"), &a, &b)?,
+          chip.gt(layouter.namespace(|| "gt"), &a, &b)?,
+          chip.max(layouter.namespace(|| "max"), &a, &b)?.cell().clone(),
+          chip.min(layouter.namespace(|| "min"), &a, &b)?.cell().clone(),
+        ];
+        for result in results {
+          layouter.constrain_instance(result.cell(), instance, row)?;
+          row += 1;
+        }
+      }
+      Ok(())
+    }
+  }
+
+  fn native(pairs: &[(u64, u64)]) -> Vec<Fp> {
+    let flag = |b: bool| Fp::from(b as u64);
+    pairs
+      .iter()
+      .flat_map(|&(a, b)| [flag(a < b), flag(a <= b), flag(a > b), Fp::from(a.max(b)), Fp::from(a.min(b))])
+      .collect()
+  }
+
+  fn run<const NUM_BITS: usize>(pairs: &[(u64, u64)], instances: Vec<Fp>) -> bool {
+    let circuit = CompareCircuit::<NUM_BITS> {
+      pairs: pairs.iter().map(|&(a, b)| (Value::known(Fp::from(a)), Value::known(Fp::from(b)))).collect(),
+    };
+    let k = crate::stats::RowUsage::measure(&circuit, &[instances.len()]).unwrap().k();
+    MockProver::run(k, &circuit, vec![instances]).unwrap().verify().is_ok()
+  }
+
+  /// SplitMix64 with a fixed seed, so a failing run draws the same pairs
+  /// again.
+  struct SplitMix(u64);
+
+  impl SplitMix {
+    fn next_u64(&mut self) -> u64 {
+      self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+      let z = (self.0 ^ (self.0 >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+      let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+      z ^ (z >> 31)
+    }
+  }
+
+  fn check_random<const NUM_BITS: usize>(seed: u64) {
+    let mut rng = SplitMix(seed);
+    let mask = if NUM_BITS == 64 { u64::MAX } else { (1 << NUM_BITS) - 1 };
+    let max = mask;
+    let mut pairs = vec![(0, 0), (0, max), (max, 0), (max, max), (max - 1, max)];
+    for _ in 0..10 {
+      let a = rng.next_u64() & mask;
+      // Equal and adjacent values are the interesting edges.
+      let b = match rng.next_u64() % 4 {
+        0 => a,
+        1 => a.saturating_add(1) & mask,
+        _ => rng.next_u64() & mask,
+      };
+      pairs.push((a, b));
+    }
+
+    let expected = native(&pairs);
+    assert!(run::<NUM_BITS>(&pairs, expected.clone()), "N = {}, pairs {:?}", NUM_BITS, pairs);
+
+    // Flipping any result is caught.
+    for i in [0, 1, 2, 3, 4, expected.len() - 1] {
+      let mut wrong = expected.clone();
+      wrong[i] += Fp::one();
+      assert!(!run::<NUM_BITS>(&pairs, wrong), "N = {}, pairs {:?}, result {}", NUM_BITS, pairs, i);
+    }
+  }
+
+  #[test]
+  fn lt_matches_native() {
+    check_random::<8>(1);
+    check_random::<13>(2);
+    check_random::<32>(3);
+    check_random::<64>(4);
+  }
+
+  #[test]
+  fn lt_checks_inputs_once() {
+    let rows = |pairs: usize| {
+      let circuit = CompareCircuit::<8> { pairs: vec![(Value::unknown(), Value::unknown()); pairs] };
+      crate::stats::RowUsage::measure(&circuit, &[]).unwrap().rows
+    };
+    // An 8-bit strict check with 8-bit words is `z_0` and `z_1 = 0`. Per
+    // pair: the two input checks, then five comparisons of one row plus the
+    // check on `low`.
+    assert_eq!(rows(1), 2 * 2 + 5 * (1 + 2));
+    assert_eq!(rows(3), 3 * rows(1));
+  }
+
+  #[test]
+  fn lt_rejects_wide_inputs() {
+    // 256 does not fit in 8 bits; otherwise `lt(256, 1)` could be forged.
+    let pairs = [(256, 1)];
+    assert!(!run::<8>(&pairs, native(&pairs)));
+  }
+
+  /// Compares two cells assigned outside the chip through
+  /// [`LtChip::check`].
+  struct ForeignCircuit {
+    a: Value<Fp>,
+    b: Value<Fp>,
+  }
+
+  impl Circuit<Fp> for ForeignCircuit {
+    type Config = (LtConfig, Column<Advice>, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+      Self { a: Value::unknown(), b: Value::unknown() }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+      let advice = meta.advice_column();
+      let instance = meta.instance_column();
+      meta.enable_equality(advice);
+      meta.enable_equality(instance);
+      (LtChip::<Fp, 8>::configure(meta, 8), advice, instance)
+    }
+
+    fn synthesize(&self, (config, advice, instance): Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+      let chip = LtChip::<Fp, 8>::construct(config);
+      chip.load(layouter.namespace(|| "word table"))?;
+
+      let (a, b) = layouter.assign_region(
+        || "foreign inputs",
+        |mut region| {
+          let a = region.assign_advice(|| "a", advice, 0, || self.a)?;
+          let b = region.assign_advice(|| "b", advice, 1, || self.b)?;
+          Ok((a, b))
+        }
+      )?;
+      let a = chip.check(layouter.namespace(|| "check a"), &a)?;
+      let b = chip.check(layouter.namespace(|| "check b"), &b)?;
+      let lt = chip.lt(layouter.namespace(|| "lt"), &a, &b)?;
+      layouter.constrain_instance(lt.cell(), instance, 0)
+    }
+  }
+
+  #[test]
+  fn lt_checks_foreign_cells() {
+    let run = |a: u64, b: u64, lt: u64| {
+      let circuit = ForeignCircuit { a: Value::known(Fp::from(a)), b: Value::known(Fp::from(b)) };
+      let k = crate::stats::RowUsage::measure(&circuit, &[1]).unwrap().k();
+      MockProver::run(k, &circuit, vec![vec![Fp::from(lt)]]).unwrap().verify().is_ok()
+    };
+
+    assert!(run(3, 200, 1));
+    assert!(run(200, 3, 0));
+    // `low = 256 − 1 = 255` fits in 8 bits, so only the input check
+    // rejects this.
+    assert!(!run(256, 1, 0));
+    assert!(!run(1, 256, 1));
+  }
+}
@@ -1,2 +1,84 @@
+//! Fibonacci and range check circuits for `halo2_proofs`, and the tooling to
+//! prove, verify and measure them.
+//!
+//! Chips live in [`gadgets`] and complete circuits in [`circuits`];
+//! [`prelude`] re-exports the common ones. Chips allocate their own columns
+//! in `configure`, so a downstream circuit composes them by configuring
+//! each one and copying cells between them:
+//!
+//! ```
+//! use halo2::{ circuits::{ fibonacci_instances, INDEX_ROW, OUT_ROW }, prelude::*, stats::minimal_k };
+//! use halo2_proofs::{
+//!   circuit::{ Layouter, SimpleFloorPlanner, Value },
+//!   pasta::Fp,
+//!   plonk::{ Circuit, ConstraintSystem, Error },
+//! };
+//!
+//! /// Proves `f(n)` from public seeds, and that it is below 256.
+//! struct SmallFibonacci {
+//!   n: usize,
+//! }
+//!
+//! impl Circuit<Fp> for SmallFibonacci {
+//!   type Config = (FibonacciConfig, RangeConfig);
+//!   type FloorPlanner = SimpleFloorPlanner;
+//!
+//!   fn without_witnesses(&self) -> Self {
+//!     Self { n: self.n }
+//!   }
+//!
+//!   fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+//!     (FibonacciChip::configure(meta), RangeChip::<Fp, 256>::configure(meta))
+//!   }
+//!
+//!   fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+//!     let fibonacci = FibonacciChip::construct(config.0);
+//!     let range = RangeChip::<Fp, 256>::construct(config.1);
+//!     range.load(layouter.namespace(|| "range table"))?;
+//!
+//!     let (out, index) = fibonacci.assign_sequence(
+//!       layouter.namespace(|| "sequence"),
+//!       SeedMode::Public,
+//!       Value::unknown(),
+//!       Value::unknown(),
+//!       self.n
+//!     )?;
+//!     range.check_cell(layouter.namespace(|| "bound"), &out)?;
+//!
+//!     fibonacci.expose_public(layouter.namespace(|| "out"), &out, OUT_ROW)?;
+//!     fibonacci.expose_public(layouter.namespace(|| "n"), &index, INDEX_ROW)
+//!   }
+//! }
+//!
+//! let one = Fp::from(1);
+//!
+//! // f(12) = 233 is in range.
+//! let instances = fibonacci_instances(SeedMode::Public, one, one, 12);
+//! assert!(minimal_k(&SmallFibonacci { n: 12 }, &instances).is_ok());
+//!
+//! // f(13) = 377 is not.
+//! let instances = fibonacci_instances(SeedMode::Public, one, one, 13);
+//! assert!(minimal_k(&SmallFibonacci { n: 13 }, &instances).is_err());
+//! ```
+
+mod bits;
+pub mod circuits;
+pub mod envelope;
 mod fibonacci;
-mod range_check;
\ No newline at end of file
+mod fibonacci_bounded;
+mod fibonacci_fast;
+mod fibonacci_rotation;
+mod is_zero;
+mod less_than;
+pub mod gadgets;
+pub mod inputs;
+pub mod keys;
+pub mod prelude;
+pub mod prover;
+mod range_check;
+mod range_check_decompose;
+mod range_check_dynamic;
+mod range_check_interval;
+mod range_check_lookup;
+mod recurrence;
+pub mod stats;
@@ -0,0 +1,52 @@
+//! The chips, configs and circuits most users need, for a glob import.
+
+pub use crate::{
+  circuits::{
+    BoundedFiboCircuit,
+    DecomposeCircuit,
+    DecomposeIntervalCircuit,
+    DynamicRangeCircuit,
+    FastFiboCircuit,
+    FiboCircuit,
+    FiboRotationCircuit,
+    IntervalCircuit,
+    RangeCircuit,
+    RangeLookupCircuit,
+    Recurrence,
+    RecurrenceCircuit,
+  },
+  gadgets::{
+    AssignedBits,
+    BitsChip,
+    BitsConfig,
+    CheckedCell,
+    DecomposeChip,
+    DecomposeConfig,
+    DecomposeIntervalChip,
+    DecomposeIntervalConfig,
+    DynamicRangeChip,
+    DynamicRangeConfig,
+    FastFibonacciChip,
+    FastFibonacciConfig,
+    FibonacciChip,
+    FibonacciConfig,
+    FibonacciInstructions,
+    FibonacciRotationChip,
+    FibonacciRotationConfig,
+    IntervalChip,
+    IntervalConfig,
+    IsEqualChip,
+    IsEqualConfig,
+    IsZeroChip,
+    IsZeroConfig,
+    LtChip,
+    LtConfig,
+    RangeChip,
+    RangeConfig,
+    RangeLookupChip,
+    RangeLookupConfig,
+    RecurrenceChip,
+    RecurrenceConfig,
+    SeedMode,
+  },
+};
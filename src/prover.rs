@@ -0,0 +1,156 @@
+use halo2_proofs::{
+  pasta::{ EqAffine, Fp },
+  plonk::{
+    create_proof,
+    keygen_pk,
+    keygen_vk,
+    verify_proof,
+    Circuit,
+    Error,
+    ProvingKey,
+    SingleVerifier,
+    VerifyingKey,
+  },
+  poly::commitment::Params,
+  transcript::{ Blake2bRead, Blake2bWrite, Challenge255 },
+};
+
+use rand_core::OsRng;
+
+/// Generates IPA parameters for `2^k` rows and the proving key for
+/// `circuit`. Keys only depend on the circuit's shape, so the witnesses in
+/// `circuit` are ignored; the verifying key is `pk.get_vk()`.
+pub fn setup<C: Circuit<Fp>>(k: u32, circuit: &C) -> Result<(Params<EqAffine>, ProvingKey<EqAffine>), Error> {
+  let params = Params::new(k);
+  let empty = circuit.without_witnesses();
+  let vk = keygen_vk(&params, &empty)?;
+  let pk = keygen_pk(&params, vk, &empty)?;
+  Ok((params, pk))
+}
+
+/// Proves `circuit` against `instances`, one vector per instance column,
+/// and returns the Blake2b transcript.
+pub fn prove<C: Circuit<Fp>>(
+  params: &Params<EqAffine>,
+  pk: &ProvingKey<EqAffine>,
+  circuit: C,
+  instances: &[Vec<Fp>]
+) -> Result<Vec<u8>, Error> {
+  let instances: Vec<&[Fp]> = instances.iter().map(Vec::as_slice).collect();
+
+  let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+  create_proof(params, pk, &[circuit], &[&instances], OsRng, &mut transcript)?;
+  Ok(transcript.finalize())
+}
+
+/// Whether `proof` is valid for `vk` and `instances`.
+pub fn verify(
+  params: &Params<EqAffine>,
+  vk: &VerifyingKey<EqAffine>,
+  proof: &[u8],
+  instances: &[Vec<Fp>]
+) -> bool {
+  let instances: Vec<&[Fp]> = instances.iter().map(Vec::as_slice).collect();
+
+  let strategy = SingleVerifier::new(params);
+  let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+  verify_proof(params, vk, strategy, &[&instances], &mut transcript).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use halo2_proofs::circuit::Value;
+
+  use super::*;
+  use crate::{
+    fibonacci::FiboCircuit,
+    fibonacci_rotation::FiboRotationCircuit,
+    range_check::RangeCircuit,
+  };
+
+  #[test]
+  fn prove_fibonacci() {
+    let circuit = FiboCircuit::<Fp>::new(9);
+    let (params, pk) = setup(circuit.k(), &circuit).unwrap();
+    let instances = circuit.instances(Fp::one(), Fp::one());
+    assert_eq!(instances[0][2], Fp::from(55));
+
+    let proof = prove(&params, &pk, circuit, &instances).unwrap();
+    assert!(verify(&params, pk.get_vk(), &proof, &instances));
+
+    // Tampered output, index and seed.
+    for row in 0..instances[0].len() {
+      let mut tampered = instances.clone();
+      tampered[0][row] += Fp::one();
+      assert!(!verify(&params, pk.get_vk(), &proof, &tampered));
+    }
+
+    // Tampered proof bytes.
+    let mut tampered = proof.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(!verify(&params, pk.get_vk(), &tampered, &instances));
+  }
+
+  #[test]
+  fn prove_fibonacci_wrong_statement() {
+    // Proving does not check constraints, but the result never verifies.
+    let circuit = FiboCircuit::<Fp>::new(9);
+    let (params, pk) = setup(circuit.k(), &circuit).unwrap();
+    let mut instances = circuit.instances(Fp::one(), Fp::one());
+    instances[0][2] = Fp::from(56);
+
+    let proof = prove(&params, &pk, circuit, &instances).unwrap();
+    assert!(!verify(&params, pk.get_vk(), &proof, &instances));
+  }
+
+  #[test]
+  fn prove_fibonacci_private_seeds() {
+    let (f0, f1) = (Fp::from(2), Fp::one());
+    let circuit = FiboCircuit::<Fp>::private(10, Value::known(f0), Value::known(f1));
+    let (params, pk) = setup(circuit.k(), &circuit).unwrap();
+    let instances = circuit.instances(f0, f1);
+
+    let proof = prove(&params, &pk, circuit, &instances).unwrap();
+    assert!(verify(&params, pk.get_vk(), &proof, &instances));
+
+    let mut tampered = instances;
+    tampered[0][0] += Fp::one();
+    assert!(!verify(&params, pk.get_vk(), &proof, &tampered));
+  }
+
+  #[test]
+  fn prove_fibonacci_rotation() {
+    let circuit = FiboRotationCircuit::<Fp>::new(20);
+    let (params, pk) = setup(circuit.k(), &circuit).unwrap();
+    let instances = circuit.instances(Fp::one(), Fp::one());
+
+    let proof = prove(&params, &pk, circuit, &instances).unwrap();
+    assert!(verify(&params, pk.get_vk(), &proof, &instances));
+
+    // A proof does not verify under another circuit's key.
+    let other = FiboCircuit::<Fp>::new(20);
+    let (_, other_pk) = setup(other.k(), &other).unwrap();
+    assert!(!verify(&params, other_pk.get_vk(), &proof, &instances));
+  }
+
+  #[test]
+  fn prove_range_check() {
+    const RANGE: usize = 8;
+    let circuit = RangeCircuit::<Fp, RANGE>::new(Fp::from(7));
+    let k = circuit.k();
+    let (params, pk) = setup(k, &circuit).unwrap();
+
+    let instances = circuit.instances();
+    let proof = prove(&params, &pk, circuit, &instances).unwrap();
+    assert!(verify(&params, pk.get_vk(), &proof, &instances));
+
+    // Extra public inputs the circuit does not have are rejected.
+    assert!(!verify(&params, pk.get_vk(), &proof, &[vec![Fp::one()]]));
+
+    // An out-of-range witness yields a proof that does not verify.
+    let circuit = RangeCircuit::<Fp, RANGE>::new(Fp::from(RANGE as u64));
+    let proof = prove(&params, &pk, circuit, &instances).unwrap();
+    assert!(!verify(&params, pk.get_vk(), &proof, &instances));
+  }
+}
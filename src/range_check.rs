@@ -9,6 +9,7 @@ use halo2_proofs::{
     Selector,
     Constraints,
     Assigned,
+    TableColumn,
   },
   circuit::*,
   poly::Rotation,
@@ -18,14 +19,64 @@ use group::ff::PrimeField;
 
 use std::marker::PhantomData;
 
+use crate::stats::k_for;
+
+/// Gate degree budget used by [`RangeChip::configure`]. The product
+/// polynomial for `RANGE` has degree `RANGE + 1`, so this keeps ranges up to
+/// 8 on the gate.
+pub const DEFAULT_MAX_DEGREE: usize = 9;
+
+/// How a [`RangeConfig`] constrains its value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeStrategy {
+  /// `v · (1 − v) · … · (RANGE − 1 − v) = 0` in a custom gate.
+  Polynomial,
+  /// Lookup into a table column holding `0..RANGE`.
+  Lookup,
+}
+
 #[derive(Clone, Debug)]
-struct RangeConfig {
-  value: Column<Advice>,
+pub struct RangeConfig {
+  values: Vec<Column<Advice>>,
   q_check: Selector,
+  table: Option<TableColumn>,
+  degree: usize,
+}
+
+impl RangeConfig {
+  pub fn strategy(&self) -> RangeStrategy {
+    match self.table {
+      Some(_) => RangeStrategy::Lookup,
+      None => RangeStrategy::Polynomial,
+    }
+  }
+
+  /// Number of advice columns a batch is spread over.
+  pub fn num_columns(&self) -> usize {
+    self.values.len()
+  }
+
+  /// Rows [`RangeChip::assign_batch`] uses for `count` values.
+  pub fn batch_rows(&self, count: usize) -> usize {
+    count.div_ceil(self.num_columns())
+  }
+
+  /// Degree the range check requires of the `ConstraintSystem`.
+  pub fn degree(&self) -> usize {
+    self.degree
+  }
+
+  /// Table rows the range check needs, on top of the checked values.
+  pub fn table_rows<const RANGE: usize>(&self) -> usize {
+    match self.strategy() {
+      RangeStrategy::Polynomial => 0,
+      RangeStrategy::Lookup => RANGE,
+    }
+  }
 }
 
 #[derive(Debug, Clone)]
-struct RangeChip<F: PrimeField, const RANGE: usize> {
+pub struct RangeChip<F: PrimeField, const RANGE: usize> {
   config: RangeConfig,
   _marker: PhantomData<F>,
 }
@@ -39,29 +90,105 @@ impl<F: PrimeField, const RANGE: usize> RangeChip<F, RANGE> {
   }
 
   pub fn configure(meta: &mut ConstraintSystem<F>) -> RangeConfig {
-    let value = meta.advice_column();
-    let q_check = meta.selector();
+    Self::configure_with_max_degree(meta, DEFAULT_MAX_DEGREE)
+  }
 
-    meta.enable_equality(value);
+  /// Uses the product-polynomial gate if its degree `RANGE + 1` fits in
+  /// `max_degree`, and a lookup table otherwise.
+  pub fn configure_with_max_degree(
+    meta: &mut ConstraintSystem<F>,
+    max_degree: usize
+  ) -> RangeConfig {
+    Self::configure_with_columns(meta, max_degree, 1)
+  }
 
-    meta.create_gate("range_check_gate", |meta| {
-      let q = meta.query_selector(q_check);
-      let value = meta.query_advice(value, Rotation::cur());
-      let range_check_poly = (1..RANGE).fold(value.clone(), |expr, i| {
-        expr * (Expression::Constant(F::from(i as u64)) - value.clone())
+  /// Like [`Self::configure_with_max_degree`], checking `num_columns` advice
+  /// columns under one selector so batches take fewer rows.
+  pub fn configure_with_columns(
+    meta: &mut ConstraintSystem<F>,
+    max_degree: usize,
+    num_columns: usize
+  ) -> RangeConfig {
+    assert!(num_columns > 0, "a range check needs at least one column");
+
+    let values: Vec<_> = (0..num_columns).map(|_| meta.advice_column()).collect();
+
+    for &value in &values {
+      meta.enable_equality(value);
+    }
+
+    // The product polynomial has degree `RANGE`, plus one for the selector.
+    if RANGE < max_degree {
+      let q_check = meta.selector();
+      let mut degree = 0;
+
+      meta.create_gate("range_check_gate", |meta| {
+        let q = meta.query_selector(q_check);
+        let constraints: Vec<_> = values
+          .iter()
+          .map(|&column| {
+            let value = meta.query_advice(column, Rotation::cur());
+            let range_check_poly = (1..RANGE).fold(value.clone(), |expr, i| {
+              expr * (Expression::Constant(F::from(i as u64)) - value.clone())
+            });
+            degree = range_check_poly.degree() + 1;
+            ("range check constraint", range_check_poly)
+          })
+          .collect();
+
+        Constraints::with_selector(q, constraints)
       });
 
-      Constraints::with_selector(q, [("range check constraint", range_check_poly)])
-    });
+      RangeConfig { values, q_check, table: None, degree }
+    } else {
+      let q_check = meta.complex_selector();
+      let table = meta.lookup_table_column();
+      let mut degree = 0;
+
+      // With the selector off the input is 0, which is always in the table.
+      for &column in &values {
+        meta.lookup(|meta| {
+          let q = meta.query_selector(q_check);
+          let value = meta.query_advice(column, Rotation::cur());
+          let input = q * value;
+          // Lookup arguments need `2 + input degree + table degree`, at least 4.
+          degree = (2 + input.degree() + 1).max(4);
+          vec![(input, table)]
+        });
+      }
+
+      RangeConfig { values, q_check, table: Some(table), degree }
+    }
+  }
+
+  /// Loads `0..RANGE` into the table column when the lookup strategy was
+  /// chosen. Call once per circuit.
+  pub fn load(&self, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let Some(table) = self.config.table else {
+      return Ok(());
+    };
 
-    RangeConfig { value, q_check }
+    layouter.assign_table(
+      || "range table",
+      |mut t| {
+        for i in 0..RANGE {
+          t.assign_cell(|| "range value", table, i, || Value::known(F::from(i as u64)))?;
+        }
+        Ok(())
+      }
+    )
   }
 
-  pub fn assign(
+  /// Witnesses `value` and range checks it. `value` may be a plain field
+  /// element or an [`Assigned`]; the returned cell can be copied into other
+  /// gadgets.
+  pub fn assign<V: Clone>(
     &self,
     mut layouter: impl Layouter<F>,
-    value: Value<Assigned<F>>
-  ) -> Result<(), Error> {
+    value: Value<V>
+  ) -> Result<AssignedCell<V, F>, Error>
+    where for<'v> Assigned<F>: From<&'v V>
+  {
     layouter.assign_region(
       || "range check region",
       |mut region| {
@@ -69,23 +196,133 @@ impl<F: PrimeField, const RANGE: usize> RangeChip<F, RANGE> {
 
         region.assign_advice(
           || "value",
-          self.config.value,
+          self.config.values[0],
           0,
-          || value
+          || value.clone()
         )
       }
-    )?;
+    )
+  }
+
+  /// Range checks a cell assigned by another gadget. The cell is copied into
+  /// the checked column under an equality constraint, so its source column
+  /// must have equality enabled.
+  pub fn check_cell<V: Clone>(
+    &self,
+    mut layouter: impl Layouter<F>,
+    cell: &AssignedCell<V, F>
+  ) -> Result<AssignedCell<V, F>, Error>
+    where for<'v> Assigned<F>: From<&'v V>
+  {
+    layouter.assign_region(
+      || "range check region",
+      |mut region| {
+        self.config.q_check.enable(&mut region, 0)?;
+
+        cell.copy_advice(|| "value", &mut region, self.config.values[0], 0)
+      }
+    )
+  }
 
+  /// Witnesses `values` and range checks them in one region, filling each
+  /// row across the config's columns before moving down. Unused cells of the
+  /// last row are set to zero, which is always in range.
+  pub fn assign_batch<V: Clone>(
+    &self,
+    mut layouter: impl Layouter<F>,
+    values: &[Value<V>]
+  ) -> Result<Vec<AssignedCell<V, F>>, Error>
+    where for<'v> Assigned<F>: From<&'v V>
+  {
+    layouter.assign_region(
+      || "range check batch",
+      |mut region| {
+        let cells = values
+          .iter()
+          .enumerate()
+          .map(|(i, value)| {
+            let (row, column) = self.batch_position(i);
+            region.assign_advice(|| format!("value {}", i), column, row, || value.clone())
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+
+        self.finish_batch(&mut region, values.len())?;
+        Ok(cells)
+      }
+    )
+  }
+
+  /// Like [`Self::assign_batch`] for cells assigned by other gadgets, which
+  /// are copied in under equality constraints.
+  pub fn check_cells<V: Clone>(
+    &self,
+    mut layouter: impl Layouter<F>,
+    cells: &[AssignedCell<V, F>]
+  ) -> Result<Vec<AssignedCell<V, F>>, Error>
+    where for<'v> Assigned<F>: From<&'v V>
+  {
+    layouter.assign_region(
+      || "range check batch",
+      |mut region| {
+        let copies = cells
+          .iter()
+          .enumerate()
+          .map(|(i, cell)| {
+            let (row, column) = self.batch_position(i);
+            cell.copy_advice(|| format!("value {}", i), &mut region, column, row)
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+
+        self.finish_batch(&mut region, cells.len())?;
+        Ok(copies)
+      }
+    )
+  }
+
+  fn batch_position(&self, index: usize) -> (usize, Column<Advice>) {
+    let columns = &self.config.values;
+    (index / columns.len(), columns[index % columns.len()])
+  }
+
+  /// Enables the selector on every batch row and zero-fills the rest of the
+  /// last one.
+  fn finish_batch(&self, region: &mut Region<'_, F>, count: usize) -> Result<(), Error> {
+    let rows = self.config.batch_rows(count);
+    for row in 0..rows {
+      self.config.q_check.enable(region, row)?;
+    }
+    for index in count..rows * self.config.num_columns() {
+      let (row, column) = self.batch_position(index);
+      region.assign_advice(|| "padding", column, row, || Value::known(F::ZERO))?;
+    }
     Ok(())
   }
 }
 
-#[derive(Default)]
-struct RangeCircuit<F: PrimeField, const RANGE: usize> {
+#[derive(Debug, Default)]
+pub struct RangeCircuit<F: PrimeField, const RANGE: usize> {
   assigned_value: Value<Assigned<F>>,
   _marker: PhantomData<F>,
 }
 
+impl<F: PrimeField, const RANGE: usize> RangeCircuit<F, RANGE> {
+  pub fn new(value: F) -> Self {
+    Self {
+      assigned_value: Value::known(value.into()),
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn instances(&self) -> Vec<Vec<F>> {
+    vec![]
+  }
+
+  /// `k` for the checked value, or the table with the lookup strategy.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
 impl<F: PrimeField, const RANGE: usize> Circuit<F> for RangeCircuit<F, RANGE> {
   type Config = RangeConfig;
   type FloorPlanner = SimpleFloorPlanner;
@@ -100,6 +337,7 @@ impl<F: PrimeField, const RANGE: usize> Circuit<F> for RangeCircuit<F, RANGE> {
 
   fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
     let chip = RangeChip::<F, RANGE>::construct(config);
+    chip.load(layouter.namespace(|| "table"))?;
     chip.assign(
       layouter.namespace(|| "value"),
       self.assigned_value
@@ -109,6 +347,24 @@ impl<F: PrimeField, const RANGE: usize> Circuit<F> for RangeCircuit<F, RANGE> {
   }
 }
 
+/// Reads `value` as an integer if it is below 2^128. Assumes the field uses
+/// a little-endian representation, as the Pasta fields do.
+pub fn field_to_u128<F: PrimeField>(value: F) -> Option<u128> {
+  let repr = value.to_repr();
+  let bytes = repr.as_ref();
+  if bytes[16..].iter().any(|&b| b != 0) {
+    return None;
+  }
+  let mut low = [0u8; 16];
+  low.copy_from_slice(&bytes[..16]);
+  Some(u128::from_le_bytes(low))
+}
+
+/// Reference for [`RangeCircuit`]: whether `0 <= value < range`.
+pub fn range_check_native<F: PrimeField>(value: F, range: usize) -> bool {
+  field_to_u128(value).is_some_and(|v| v < range as u128)
+}
+
 #[cfg(test)]
 mod tests {
   use halo2_proofs::{ dev::{ FailureLocation, MockProver, VerifyFailure }, pasta::Fp, plonk::* };
@@ -160,4 +416,226 @@ mod tests {
       );
     }
   }
+
+  #[test]
+  fn test_range_strategy() {
+    let mut meta = ConstraintSystem::<Fp>::default();
+    let config = RangeChip::<Fp, 8>::configure(&mut meta);
+    assert_eq!(config.strategy(), RangeStrategy::Polynomial);
+    assert_eq!(config.degree(), 9);
+    assert_eq!(meta.degree(), 9);
+
+    let mut meta = ConstraintSystem::<Fp>::default();
+    let config = RangeChip::<Fp, 256>::configure(&mut meta);
+    assert_eq!(config.strategy(), RangeStrategy::Lookup);
+    assert_eq!(config.degree(), 5);
+    assert_eq!(meta.degree(), 5);
+
+    let mut meta = ConstraintSystem::<Fp>::default();
+    let config = RangeChip::<Fp, 3>::configure_with_max_degree(&mut meta, 4);
+    assert_eq!(config.strategy(), RangeStrategy::Polynomial);
+    assert_eq!(config.degree(), 4);
+
+    let mut meta = ConstraintSystem::<Fp>::default();
+    let config = RangeChip::<Fp, 4>::configure_with_max_degree(&mut meta, 4);
+    assert_eq!(config.strategy(), RangeStrategy::Lookup);
+  }
+
+  #[test]
+  fn test_range_check_lookup_strategy() {
+    const RANGE: usize = 256;
+    let k = RangeCircuit::<Fp, RANGE>::new(Fp::zero()).k();
+
+    for value in [0, 1, RANGE as u64 - 1] {
+      let circuit = RangeCircuit::<Fp, RANGE>::new(Fp::from(value));
+      let prover = MockProver::run(k, &circuit, circuit.instances()).unwrap();
+      prover.assert_satisfied();
+    }
+
+    let circuit = RangeCircuit::<Fp, RANGE>::new(Fp::from(RANGE as u64));
+    let prover = MockProver::run(k, &circuit, circuit.instances()).unwrap();
+    assert_eq!(
+      prover.verify(),
+      Err(
+        vec![VerifyFailure::Lookup {
+          lookup_index: 0,
+          location: FailureLocation::InRegion {
+            region: (1, "range check region").into(),
+            offset: 0,
+          },
+        }]
+      )
+    );
+  }
+
+  /// Witnesses a value in its own column and range checks the cell.
+  #[derive(Default)]
+  struct CheckCellCircuit<const RANGE: usize> {
+    value: Value<Fp>,
+  }
+
+  impl<const RANGE: usize> Circuit<Fp> for CheckCellCircuit<RANGE> {
+    type Config = (Column<Advice>, RangeConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+      Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+      let source = meta.advice_column();
+      meta.enable_equality(source);
+      (source, RangeChip::<Fp, RANGE>::configure(meta))
+    }
+
+    fn synthesize(&self, (source, config): Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+      let cell = layouter.assign_region(
+        || "source",
+        |mut region| region.assign_advice(|| "source", source, 0, || self.value)
+      )?;
+
+      let chip = RangeChip::<Fp, RANGE>::construct(config);
+      chip.load(layouter.namespace(|| "table"))?;
+      let checked = chip.check_cell(layouter.namespace(|| "check"), &cell)?;
+      checked.value().zip(self.value).assert_if_known(|(a, b)| *a == b);
+
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn test_range_check_cell() {
+    const RANGE: usize = 8;
+
+    let circuit = CheckCellCircuit::<RANGE> { value: Value::known(Fp::from(7)) };
+    MockProver::run(4, &circuit, vec![]).unwrap().assert_satisfied();
+
+    let circuit = CheckCellCircuit::<RANGE> { value: Value::known(Fp::from(8)) };
+    assert_eq!(
+      MockProver::run(4, &circuit, vec![]).unwrap().verify(),
+      Err(
+        vec![VerifyFailure::ConstraintNotSatisfied {
+          constraint: ((0, "range_check_gate").into(), 0, "range check constraint").into(),
+          location: FailureLocation::InRegion {
+            region: (1, "range check region").into(),
+            offset: 0,
+          },
+          cell_values: vec![(((Any::Advice, 1).into(), 0).into(), "0x8".to_string())],
+        }]
+      )
+    );
+
+    // Lookup strategy: the copied cell is looked up like a fresh witness.
+    let circuit = CheckCellCircuit::<256> { value: Value::known(Fp::from(255)) };
+    MockProver::run(9, &circuit, vec![]).unwrap().assert_satisfied();
+    let circuit = CheckCellCircuit::<256> { value: Value::known(Fp::from(256)) };
+    assert!(MockProver::run(9, &circuit, vec![]).unwrap().verify().is_err());
+  }
+
+  /// Range checks `values` either one region per value or as one batch
+  /// over `COLUMNS` columns.
+  #[derive(Clone, Debug, Default)]
+  struct BatchCircuit<const RANGE: usize, const COLUMNS: usize> {
+    values: Vec<Value<Fp>>,
+    batch: bool,
+  }
+
+  impl<const RANGE: usize, const COLUMNS: usize> Circuit<Fp> for BatchCircuit<RANGE, COLUMNS> {
+    type Config = RangeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+      Self {
+        values: vec![Value::unknown(); self.values.len()],
+        batch: self.batch,
+      }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+      RangeChip::<Fp, RANGE>::configure_with_columns(meta, DEFAULT_MAX_DEGREE, COLUMNS)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+      let chip = RangeChip::<Fp, RANGE>::construct(config);
+      chip.load(layouter.namespace(|| "table"))?;
+
+      if self.batch {
+        let cells = chip.assign_batch(layouter.namespace(|| "batch"), &self.values)?;
+        assert_eq!(cells.len(), self.values.len());
+      } else {
+        for value in &self.values {
+          chip.assign(layouter.namespace(|| "value"), *value)?;
+        }
+      }
+
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn test_range_check_batch() {
+    const RANGE: usize = 8;
+    let values: Vec<_> = (0..10).map(|i| Value::known(Fp::from(i % RANGE as u64))).collect();
+
+    let circuit = BatchCircuit::<RANGE, 3> { values: values.clone(), batch: true };
+    MockProver::run(5, &circuit, vec![]).unwrap().assert_satisfied();
+
+    let mut bad = values;
+    bad[7] = Value::known(Fp::from(RANGE as u64));
+    let circuit = BatchCircuit::<RANGE, 3> { values: bad, batch: true };
+    assert_eq!(
+      MockProver::run(5, &circuit, vec![]).unwrap().verify(),
+      Err(
+        vec![VerifyFailure::ConstraintNotSatisfied {
+          constraint: ((0, "range_check_gate").into(), 1, "range check constraint").into(),
+          location: FailureLocation::InRegion {
+            region: (0, "range check batch").into(),
+            offset: 2,
+          },
+          cell_values: vec![(((Any::Advice, 1).into(), 0).into(), "0x8".to_string())],
+        }]
+      )
+    );
+
+    // Lookup strategy over two columns.
+    let values: Vec<_> = (0..9).map(|i| Value::known(Fp::from(i * 31))).collect();
+    let circuit = BatchCircuit::<256, 2> { values, batch: true };
+    MockProver::run(9, &circuit, vec![]).unwrap().assert_satisfied();
+  }
+
+  #[test]
+  fn compare_batch_rows() {
+    const RANGE: usize = 8;
+    let count = 1000;
+    let values: Vec<_> = (0..count).map(|i| Value::known(Fp::from((i % RANGE) as u64))).collect();
+    let k = 11;
+
+    fn advice_rows<const COLUMNS: usize>(k: u32, circuit: &BatchCircuit<8, COLUMNS>) -> usize {
+      MockProver::run(k, circuit, vec![]).unwrap().assert_satisfied();
+      crate::stats::RowUsage::measure(circuit, &[]).unwrap().rows
+    }
+
+    let single = advice_rows(k, &BatchCircuit::<RANGE, 1> { values: values.clone(), batch: false });
+    let batch = advice_rows(k, &BatchCircuit::<RANGE, 1> { values: values.clone(), batch: true });
+    let wide = advice_rows(k, &BatchCircuit::<RANGE, 4> { values, batch: true });
+
+    // One region per value and one batched region use the same rows;
+    // spreading the batch over four columns divides them by four.
+    assert_eq!(single, count);
+    assert_eq!(batch, single);
+    assert_eq!(wide, count / 4);
+  }
+
+  #[test]
+  fn range_check_native_matches_circuit() {
+    let k = 4;
+    const RANGE: usize = 8;
+
+    let values = (0..2 * RANGE as u64).map(Fp::from).chain([-Fp::one(), -Fp::from(RANGE as u64)]);
+    for value in values {
+      let circuit = RangeCircuit::<Fp, RANGE>::new(value);
+      let prover = MockProver::run(k, &circuit, circuit.instances()).unwrap();
+      assert_eq!(prover.verify().is_ok(), range_check_native(value, RANGE));
+    }
+  }
 }
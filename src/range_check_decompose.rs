@@ -0,0 +1,412 @@
+use halo2_proofs::{
+  plonk::{
+    Advice,
+    Circuit,
+    Column,
+    ConstraintSystem,
+    Constraints,
+    Error,
+    Expression,
+    Fixed,
+    Selector,
+    TableColumn,
+  },
+  circuit::*,
+  poly::Rotation,
+};
+
+use group::ff::PrimeField;
+
+use std::marker::PhantomData;
+
+use crate::stats::k_for;
+
+/// Range check for wide values by running-sum decomposition into `K`-bit
+/// words. With `z_0` the value, each row holds
+///
+///   `z_{i+1} = (z_i − word_i) / 2^K`, so `word_i = z_i − 2^K · z_{i+1}`
+///
+/// and every `word_i` is looked up in a `0..2^K` table. Bit lengths that are
+/// not a multiple of `K` end with a short range check on the last `z`: both
+/// it and `z · 2^(K − s)` must be in the table, which holds iff `z < 2^s`.
+#[derive(Clone, Debug)]
+pub struct DecomposeConfig {
+  running_sum: Column<Advice>,
+  q_lookup: Selector,
+  q_running: Selector,
+  q_bitshift: Selector,
+  shift: Column<Fixed>,
+  table: TableColumn,
+}
+
+/// The running sum `z_0, .., z_n`, where `z_0` is the checked value.
+pub type RunningSum<F> = Vec<AssignedCell<F, F>>;
+
+#[derive(Debug, Clone)]
+pub struct DecomposeChip<F: PrimeField, const K: usize> {
+  config: DecomposeConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField, const K: usize> DecomposeChip<F, K> {
+  pub fn construct(config: DecomposeConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>) -> DecomposeConfig {
+    let running_sum = meta.advice_column();
+    let config = Self::configure_with_column(meta, running_sum);
+
+    let constant = meta.fixed_column();
+    meta.enable_constant(constant);
+
+    config
+  }
+
+  /// Like [`Self::configure`] with the running sum in a column owned by the
+  /// caller. Strict checks fix the last `z` to zero, so the caller must
+  /// register a column for constants.
+  pub fn configure_with_column(
+    meta: &mut ConstraintSystem<F>,
+    running_sum: Column<Advice>
+  ) -> DecomposeConfig {
+    assert!(0 < K && K < 32, "the table holds 2^K rows");
+
+    let q_lookup = meta.complex_selector();
+    let q_running = meta.complex_selector();
+    let q_bitshift = meta.selector();
+    let shift = meta.fixed_column();
+    let table = meta.lookup_table_column();
+
+    meta.enable_equality(running_sum);
+
+    // Running-sum rows look up `z_i − 2^K · z_{i+1}`; short-check rows look up
+    // the cell itself. With `q_lookup` off the input is 0.
+    meta.lookup(|meta| {
+      let q_lookup = meta.query_selector(q_lookup);
+      let q_running = meta.query_selector(q_running);
+      let z_cur = meta.query_advice(running_sum, Rotation::cur());
+      let z_next = meta.query_advice(running_sum, Rotation::next());
+
+      let running_word = z_cur.clone() - z_next * F::from(1 << K);
+      let one = Expression::Constant(F::ONE);
+      let word = q_running.clone() * running_word + (one - q_running) * z_cur;
+
+      vec![(q_lookup * word, table)]
+    });
+
+    meta.create_gate("short range bitshift", |meta| {
+      let q = meta.query_selector(q_bitshift);
+      let word = meta.query_advice(running_sum, Rotation::prev());
+      let shifted = meta.query_advice(running_sum, Rotation::cur());
+      let shift = meta.query_fixed(shift);
+
+      Constraints::with_selector(q, [("shifted = word * 2^(K - s)", shifted - word * shift)])
+    });
+
+    DecomposeConfig {
+      running_sum,
+      q_lookup,
+      q_running,
+      q_bitshift,
+      shift,
+      table,
+    }
+  }
+
+  /// Loads `0..2^K` into the table column. Call once per circuit.
+  pub fn load(&self, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    layouter.assign_table(
+      || "word table",
+      |mut table| {
+        for i in 0..1 << K {
+          table.assign_cell(
+            || "word",
+            self.config.table,
+            i,
+            || Value::known(F::from(i as u64))
+          )?;
+        }
+        Ok(())
+      }
+    )
+  }
+
+  /// Range checks an existing cell by copying it into the running sum.
+  ///
+  /// In strict mode the value is proven to fit in exactly `num_bits` bits.
+  /// In loose mode `num_bits` is rounded up to a multiple of `K` and the
+  /// final `z` is left unconstrained for the caller to inspect.
+  pub fn copy_check(
+    &self,
+    mut layouter: impl Layouter<F>,
+    element: &AssignedCell<F, F>,
+    num_bits: usize,
+    strict: bool
+  ) -> Result<RunningSum<F>, Error> {
+    let zs = layouter.assign_region(
+      || "decompose",
+      |mut region| {
+        let z_0 = element.copy_advice(|| "z_0", &mut region, self.config.running_sum, 0)?;
+        self.running_sum(&mut region, z_0, Self::num_words(num_bits, strict))
+      }
+    )?;
+
+    self.finish(layouter, zs, num_bits, strict)
+  }
+
+  /// Like [`Self::copy_check`] for a fresh witness.
+  pub fn witness_check(
+    &self,
+    mut layouter: impl Layouter<F>,
+    value: Value<F>,
+    num_bits: usize,
+    strict: bool
+  ) -> Result<RunningSum<F>, Error> {
+    let zs = layouter.assign_region(
+      || "decompose",
+      |mut region| {
+        let z_0 = region.assign_advice(|| "z_0", self.config.running_sum, 0, || value)?;
+        self.running_sum(&mut region, z_0, Self::num_words(num_bits, strict))
+      }
+    )?;
+
+    self.finish(layouter, zs, num_bits, strict)
+  }
+
+  /// Proves `element < 2^num_bits` for `num_bits < K` with two lookups.
+  pub fn short_check(
+    &self,
+    mut layouter: impl Layouter<F>,
+    element: &AssignedCell<F, F>,
+    num_bits: usize
+  ) -> Result<(), Error> {
+    assert!(num_bits < K, "short checks are for fewer than K bits");
+
+    layouter.assign_region(
+      || "short range check",
+      |mut region| {
+        self.config.q_lookup.enable(&mut region, 0)?;
+        element.copy_advice(|| "word", &mut region, self.config.running_sum, 0)?;
+
+        let shift = F::from(1 << (K - num_bits));
+        self.config.q_lookup.enable(&mut region, 1)?;
+        self.config.q_bitshift.enable(&mut region, 1)?;
+        region.assign_fixed(|| "2^(K - s)", self.config.shift, 1, || Value::known(shift))?;
+        region.assign_advice(
+          || "shifted word",
+          self.config.running_sum,
+          1,
+          || element.value().map(|word| *word * shift)
+        )?;
+
+        Ok(())
+      }
+    )
+  }
+
+  fn num_words(num_bits: usize, strict: bool) -> usize {
+    assert!(num_bits < F::NUM_BITS as usize, "the value must not wrap the field");
+    if strict { num_bits / K } else { num_bits.div_ceil(K) }
+  }
+
+  fn running_sum(
+    &self,
+    region: &mut Region<'_, F>,
+    z_0: AssignedCell<F, F>,
+    num_words: usize
+  ) -> Result<RunningSum<F>, Error> {
+    let inv_two_pow_k = F::from(1 << K).invert().unwrap();
+
+    let mut zs = vec![z_0];
+    for i in 0..num_words {
+      self.config.q_lookup.enable(region, i)?;
+      self.config.q_running.enable(region, i)?;
+
+      let z_cur = zs[i].value().copied();
+      let z_next = z_cur.map(|z| (z - lower_bits(&z, K)) * inv_two_pow_k);
+      zs.push(region.assign_advice(|| "z", self.config.running_sum, i + 1, || z_next)?);
+    }
+
+    Ok(zs)
+  }
+
+  /// In strict mode, constrains whatever is left after the full words: zero
+  /// if `num_bits` is a multiple of `K`, below `2^(num_bits mod K)` otherwise.
+  fn finish(
+    &self,
+    mut layouter: impl Layouter<F>,
+    zs: RunningSum<F>,
+    num_bits: usize,
+    strict: bool
+  ) -> Result<RunningSum<F>, Error> {
+    if strict {
+      let z_last = zs.last().unwrap();
+      match num_bits % K {
+        0 => {
+          layouter.assign_region(
+            || "final z",
+            |mut region| region.constrain_constant(z_last.cell(), F::ZERO)
+          )?;
+        }
+        rem => self.short_check(layouter.namespace(|| "remaining bits"), z_last, rem)?,
+      }
+    }
+
+    Ok(zs)
+  }
+}
+
+/// The low `num_bits` bits of `value`, for `num_bits <= 64`. Assumes a
+/// little-endian representation, as the Pasta fields use.
+pub fn lower_bits<F: PrimeField>(value: &F, num_bits: usize) -> F {
+  assert!(num_bits <= 64);
+  let repr = value.to_repr();
+  let mut low = [0u8; 8];
+  low.copy_from_slice(&repr.as_ref()[..8]);
+  let low = u64::from_le_bytes(low);
+  F::from(if num_bits == 64 { low } else { low & ((1 << num_bits) - 1) })
+}
+
+#[derive(Clone, Debug)]
+pub struct DecomposeCircuit<F: PrimeField, const K: usize> {
+  pub value: Value<F>,
+  pub num_bits: usize,
+  pub strict: bool,
+}
+
+impl<F: PrimeField, const K: usize> DecomposeCircuit<F, K> {
+  pub fn new(value: F, num_bits: usize, strict: bool) -> Self {
+    Self {
+      value: Value::known(value),
+      num_bits,
+      strict,
+    }
+  }
+
+  pub fn instances(&self) -> Vec<Vec<F>> {
+    vec![]
+  }
+
+  /// `k` for the word table and the running sum.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
+impl<F: PrimeField, const K: usize> Circuit<F> for DecomposeCircuit<F, K> {
+  type Config = DecomposeConfig;
+  type FloorPlanner = SimpleFloorPlanner;
+
+  fn without_witnesses(&self) -> Self {
+    Self {
+      value: Value::unknown(),
+      ..self.clone()
+    }
+  }
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    DecomposeChip::<F, K>::configure(meta)
+  }
+
+  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let chip = DecomposeChip::<F, K>::construct(config);
+    chip.load(layouter.namespace(|| "table"))?;
+    chip.witness_check(layouter.namespace(|| "value"), self.value, self.num_bits, self.strict)?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use halo2_proofs::{ arithmetic::Field, dev::MockProver, pasta::Fp };
+
+  use super::*;
+
+  fn pow2(bits: u32) -> Fp {
+    Fp::from(2).pow_vartime([bits as u64])
+  }
+
+  fn check<const K: usize>(value: Fp, num_bits: usize, strict: bool) -> bool {
+    let circuit = DecomposeCircuit::<Fp, K>::new(value, num_bits, strict);
+    let prover = MockProver::run(circuit.k(), &circuit, circuit.instances()).unwrap();
+    prover.verify().is_ok()
+  }
+
+  #[test]
+  fn test_decompose_64() {
+    assert!(check::<8>(Fp::zero(), 64, true));
+    assert!(check::<8>(Fp::from(u64::MAX), 64, true));
+    assert!(!check::<8>(pow2(64), 64, true));
+    assert!(!check::<8>(-Fp::one(), 64, true));
+  }
+
+  #[test]
+  fn test_decompose_128() {
+    assert!(check::<8>(pow2(128) - Fp::one(), 128, true));
+    assert!(!check::<8>(pow2(128), 128, true));
+  }
+
+  #[test]
+  fn test_decompose_short_remainder() {
+    // 66 = 8 · 8 + 2, so the last z gets a 2-bit short check.
+    assert!(check::<8>(pow2(66) - Fp::one(), 66, true));
+    assert!(!check::<8>(pow2(66), 66, true));
+
+    // Fewer bits than one word.
+    assert!(check::<8>(Fp::from(31), 5, true));
+    assert!(!check::<8>(Fp::from(32), 5, true));
+  }
+
+  #[test]
+  fn test_decompose_loose() {
+    // Loose mode rounds 60 bits up to 64 and leaves the final z free.
+    assert!(check::<8>(pow2(60), 60, false));
+    assert!(check::<8>(pow2(64), 60, false));
+    assert!(!check::<8>(pow2(60), 60, true));
+  }
+
+  #[derive(Clone, Debug)]
+  struct CopyCircuit {
+    value: Value<Fp>,
+  }
+
+  impl Circuit<Fp> for CopyCircuit {
+    type Config = (Column<Advice>, DecomposeConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+      Self { value: Value::unknown() }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+      let input = meta.advice_column();
+      meta.enable_equality(input);
+      (input, DecomposeChip::<Fp, 4>::configure(meta))
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+      let chip = DecomposeChip::<Fp, 4>::construct(config.1);
+      chip.load(layouter.namespace(|| "table"))?;
+
+      let cell = layouter.assign_region(
+        || "input",
+        |mut region| region.assign_advice(|| "input", config.0, 0, || self.value)
+      )?;
+      let zs = chip.copy_check(layouter.namespace(|| "check"), &cell, 16, false)?;
+      assert_eq!(zs.len(), 5);
+
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn test_decompose_copy_loose() {
+    let circuit = CopyCircuit { value: Value::known(Fp::from(0xbeef)) };
+    MockProver::run(6, &circuit, vec![]).unwrap().assert_satisfied();
+  }
+}
@@ -0,0 +1,325 @@
+use halo2_proofs::{
+  plonk::{
+    Advice,
+    Circuit,
+    Column,
+    ConstraintSystem,
+    Constraints,
+    Error,
+    Expression,
+    Instance,
+    Selector,
+  },
+  circuit::*,
+  poly::Rotation,
+};
+
+use group::ff::PrimeField;
+
+use std::marker::PhantomData;
+
+use crate::{
+  range_check::field_to_u128,
+  range_check_decompose::{ DecomposeChip, DecomposeConfig },
+  stats::k_for,
+};
+
+/// Proves `v < bound` where `bound` is a cell, so it can come from a public
+/// input and change between proofs without changing the verifying key.
+///
+/// With `n` the bit width fixed at configure time, both `v` and
+/// `bound − v − 1` are range checked to `n` bits. Neither can wrap, so
+/// `bound = v + (bound − v − 1) + 1` holds over the integers and `v < bound`.
+///
+/// The difference only fits in `n` bits for every `v < bound` when
+/// `bound ≤ 2^n`, so [`DynamicRangeChip::assign_bound`] also checks
+/// `bound − 1` to `n` bits. Larger bounds, and a zero bound, make the
+/// circuit unsatisfiable rather than leave some values below them unprovable.
+#[derive(Clone, Debug)]
+pub struct DynamicRangeConfig {
+  cells: Column<Advice>,
+  instance: Column<Instance>,
+  q_diff: Selector,
+  q_bound: Selector,
+  num_bits: usize,
+  decompose: DecomposeConfig,
+}
+
+impl DynamicRangeConfig {
+  /// Bit width of the checked values.
+  pub fn num_bits(&self) -> usize {
+    self.num_bits
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct DynamicRangeChip<F: PrimeField, const K: usize> {
+  config: DynamicRangeConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField, const K: usize> DynamicRangeChip<F, K> {
+  pub fn construct(config: DynamicRangeConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>, num_bits: usize) -> DynamicRangeConfig {
+    let cells = meta.advice_column();
+    let instance = meta.instance_column();
+    let running_sum = meta.advice_column();
+    let config = Self::configure_with_columns(meta, num_bits, cells, instance, running_sum);
+
+    let constant = meta.fixed_column();
+    meta.enable_constant(constant);
+
+    config
+  }
+
+  /// Like [`Self::configure`] on columns owned by the caller, who must also
+  /// register a column for constants. `cells` holds the value, the bound
+  /// and their difference, and the decompositions run in `running_sum`;
+  /// both may be shared with other chips.
+  pub fn configure_with_columns(
+    meta: &mut ConstraintSystem<F>,
+    num_bits: usize,
+    cells: Column<Advice>,
+    instance: Column<Instance>,
+    running_sum: Column<Advice>
+  ) -> DynamicRangeConfig {
+    assert!(num_bits + 1 < F::NUM_BITS as usize, "v + (bound - v - 1) must not wrap the field");
+
+    let q_diff = meta.selector();
+    let q_bound = meta.selector();
+
+    meta.enable_equality(cells);
+    meta.enable_equality(instance);
+
+    // Rows: value, bound, bound − value − 1.
+    meta.create_gate("bound difference", |meta| {
+      let q = meta.query_selector(q_diff);
+      let value = meta.query_advice(cells, Rotation::cur());
+      let bound = meta.query_advice(cells, Rotation::next());
+      let diff = meta.query_advice(cells, Rotation(2));
+      let one = Expression::Constant(F::ONE);
+
+      Constraints::with_selector(q, [("diff = bound - value - 1", diff - (bound - value - one))])
+    });
+
+    // Rows: bound, bound − 1.
+    meta.create_gate("bound minus one", |meta| {
+      let q = meta.query_selector(q_bound);
+      let bound = meta.query_advice(cells, Rotation::cur());
+      let bound_minus_one = meta.query_advice(cells, Rotation::next());
+      let one = Expression::Constant(F::ONE);
+
+      Constraints::with_selector(q, [("bound_minus_one = bound - 1", bound_minus_one - (bound - one))])
+    });
+
+    DynamicRangeConfig {
+      cells,
+      instance,
+      q_diff,
+      q_bound,
+      num_bits,
+      decompose: DecomposeChip::<F, K>::configure_with_column(meta, running_sum),
+    }
+  }
+
+  /// Loads the decomposition word table. Call once per circuit.
+  pub fn load(&self, layouter: impl Layouter<F>) -> Result<(), Error> {
+    DecomposeChip::<F, K>::construct(self.config.decompose.clone()).load(layouter)
+  }
+
+  /// Witnesses `value` and checks it against the public bound on instance
+  /// row `row`.
+  pub fn assign(
+    &self,
+    mut layouter: impl Layouter<F>,
+    value: Value<F>,
+    row: usize
+  ) -> Result<AssignedCell<F, F>, Error> {
+    let value = layouter.assign_region(
+      || "dynamic range value",
+      |mut region| region.assign_advice(|| "value", self.config.cells, 0, || value)
+    )?;
+    let bound = self.assign_bound(layouter.namespace(|| "bound"), row)?;
+    self.check_cell(layouter, &value, &bound)?;
+
+    Ok(value)
+  }
+
+  /// Copies the public bound on instance row `row` into an advice cell, for
+  /// [`Self::check_cell`], and checks `1 ≤ bound ≤ 2^n`.
+  pub fn assign_bound(&self, mut layouter: impl Layouter<F>, row: usize) -> Result<AssignedCell<F, F>, Error> {
+    let (bound, bound_minus_one) = layouter.assign_region(
+      || "bound",
+      |mut region| {
+        self.config.q_bound.enable(&mut region, 0)?;
+        let bound = region.assign_advice_from_instance(|| "bound", self.config.instance, row, self.config.cells, 0)?;
+        let bound_minus_one = bound.value().map(|b| *b - F::ONE);
+        let bound_minus_one = region.assign_advice(|| "bound - 1", self.config.cells, 1, || bound_minus_one)?;
+        Ok((bound, bound_minus_one))
+      }
+    )?;
+
+    DecomposeChip::<F, K>::construct(self.config.decompose.clone()).copy_check(
+      layouter.namespace(|| "bound - 1"),
+      &bound_minus_one,
+      self.config.num_bits,
+      true
+    )?;
+
+    Ok(bound)
+  }
+
+  /// Checks `value < bound` for two cells assigned by other gadgets. Every
+  /// value below the bound is provable when `bound ≤ 2^n`, which
+  /// [`Self::assign_bound`] checks.
+  pub fn check_cell(
+    &self,
+    mut layouter: impl Layouter<F>,
+    value: &AssignedCell<F, F>,
+    bound: &AssignedCell<F, F>
+  ) -> Result<(), Error> {
+    let (value, diff) = layouter.assign_region(
+      || "dynamic range check",
+      |mut region| {
+        self.config.q_diff.enable(&mut region, 0)?;
+        let value = value.copy_advice(|| "value", &mut region, self.config.cells, 0)?;
+        let bound = bound.copy_advice(|| "bound", &mut region, self.config.cells, 1)?;
+
+        let diff = value
+          .value()
+          .zip(bound.value())
+          .map(|(v, b)| *b - v - F::ONE);
+        let diff = region.assign_advice(|| "bound - value - 1", self.config.cells, 2, || diff)?;
+
+        Ok((value, diff))
+      }
+    )?;
+
+    let decompose = DecomposeChip::<F, K>::construct(self.config.decompose.clone());
+    let num_bits = self.config.num_bits;
+    decompose.copy_check(layouter.namespace(|| "value"), &value, num_bits, true)?;
+    decompose.copy_check(layouter.namespace(|| "difference"), &diff, num_bits, true)?;
+
+    Ok(())
+  }
+}
+
+/// Proves the witness is below the bound on instance row 0. Values are
+/// `NUM_BITS` wide; the bound is public, so one verifying key covers every
+/// bound from 1 up to `2^NUM_BITS`. Other bounds are rejected.
+#[derive(Clone, Debug)]
+pub struct DynamicRangeCircuit<F: PrimeField, const K: usize, const NUM_BITS: usize> {
+  pub value: Value<F>,
+}
+
+impl<F: PrimeField, const K: usize, const NUM_BITS: usize> DynamicRangeCircuit<F, K, NUM_BITS> {
+  pub fn new(value: F) -> Self {
+    Self { value: Value::known(value) }
+  }
+
+  /// The public bound is the only public input.
+  pub fn instances(bound: F) -> Vec<Vec<F>> {
+    vec![vec![bound]]
+  }
+
+  /// `k` for the word table and the three decompositions.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
+impl<F: PrimeField, const K: usize, const NUM_BITS: usize> Circuit<F>
+for DynamicRangeCircuit<F, K, NUM_BITS> {
+  type Config = DynamicRangeConfig;
+  type FloorPlanner = SimpleFloorPlanner;
+
+  fn without_witnesses(&self) -> Self {
+    Self { value: Value::unknown() }
+  }
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    DynamicRangeChip::<F, K>::configure(meta, NUM_BITS)
+  }
+
+  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let chip = DynamicRangeChip::<F, K>::construct(config);
+    chip.load(layouter.namespace(|| "table"))?;
+    chip.assign(layouter.namespace(|| "value"), self.value, 0)?;
+
+    Ok(())
+  }
+}
+
+/// Reference for [`DynamicRangeCircuit`]: whether `value < bound` with
+/// `bound` at most `2^num_bits`, so `value` fits in `num_bits` bits.
+pub fn dynamic_range_native<F: PrimeField>(value: F, bound: F, num_bits: usize) -> bool {
+  match (field_to_u128(value), field_to_u128(bound)) {
+    (Some(v), Some(b)) => v < b && (b - 1).checked_shr(num_bits as u32).unwrap_or(0) == 0,
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use halo2_proofs::{ dev::MockProver, pasta::Fp };
+
+  use super::*;
+
+  type Circuit64 = DynamicRangeCircuit<Fp, 8, 64>;
+
+  fn check(value: Fp, bound: Fp) -> bool {
+    let circuit = Circuit64::new(value);
+    let prover = MockProver::run(circuit.k(), &circuit, Circuit64::instances(bound)).unwrap();
+    prover.verify().is_ok()
+  }
+
+  #[test]
+  fn test_dynamic_range_edges() {
+    for bound in [1u64, 8, 120, 1 << 32, u64::MAX] {
+      let bound = Fp::from(bound);
+      assert!(check(Fp::zero(), bound));
+      assert!(check(bound - Fp::one(), bound));
+      assert!(!check(bound, bound));
+      assert!(!check(bound + Fp::one(), bound));
+      assert!(!check(-Fp::one(), bound));
+    }
+  }
+
+  #[test]
+  fn test_dynamic_range_zero_bound() {
+    assert!(!check(Fp::zero(), Fp::zero()));
+    assert!(!check(-Fp::one(), Fp::zero()));
+  }
+
+  #[test]
+  fn test_dynamic_range_matches_native() {
+    let two_pow_64 = Fp::from_u128(1 << 64);
+    let values = [Fp::zero(), Fp::one(), Fp::from(17), Fp::from(120), Fp::from(1 << 40), two_pow_64, two_pow_64 + Fp::one()];
+    for &v in &values {
+      for &b in &values {
+        assert_eq!(check(v, b), dynamic_range_native(v, b, 64), "{:?} < {:?}", v, b);
+      }
+    }
+  }
+
+  #[test]
+  fn test_dynamic_range_bound_above_two_pow_n() {
+    // 2^64 is the largest bound, and every 64-bit value is below it.
+    let two_pow_64 = Fp::from_u128(1 << 64);
+    assert!(check(Fp::zero(), two_pow_64));
+    assert!(check(Fp::from(u64::MAX), two_pow_64));
+
+    // Past it the bound itself is rejected, even for values far below.
+    for bound in [two_pow_64 + Fp::one(), Fp::from_u128(1 << 100)] {
+      assert!(!check(Fp::zero(), bound));
+      assert!(!check(Fp::from(u64::MAX), bound));
+      assert!(!dynamic_range_native(Fp::zero(), bound, 64));
+    }
+  }
+}
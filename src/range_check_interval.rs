@@ -0,0 +1,408 @@
+use halo2_proofs::{
+  plonk::{
+    Advice,
+    Assigned,
+    Circuit,
+    Column,
+    ConstraintSystem,
+    Constraints,
+    Error,
+    Expression,
+    Fixed,
+    Selector,
+  },
+  circuit::*,
+  poly::Rotation,
+};
+
+use group::ff::PrimeField;
+
+use std::marker::PhantomData;
+
+use crate::{
+  range_check::field_to_u128,
+  range_check_decompose::{ DecomposeChip, DecomposeConfig },
+  stats::k_for,
+};
+
+/// Proves `LO <= v < HI` with the product polynomial
+/// `(v − LO) · (v − LO − 1) · … · (v − HI + 1) = 0`, of degree `HI − LO + 1`
+/// with the selector. Meant for short intervals; see [`DecomposeIntervalChip`]
+/// for wide ones.
+#[derive(Clone, Debug)]
+pub struct IntervalConfig {
+  value: Column<Advice>,
+  q_check: Selector,
+}
+
+#[derive(Debug, Clone)]
+pub struct IntervalChip<F: PrimeField, const LO: usize, const HI: usize> {
+  config: IntervalConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField, const LO: usize, const HI: usize> IntervalChip<F, LO, HI> {
+  pub fn construct(config: IntervalConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>) -> IntervalConfig {
+    assert!(LO < HI, "the interval must not be empty");
+
+    let value = meta.advice_column();
+    let q_check = meta.selector();
+
+    meta.enable_equality(value);
+
+    meta.create_gate("interval check", |meta| {
+      let q = meta.query_selector(q_check);
+      let value = meta.query_advice(value, Rotation::cur());
+      let poly = (LO..HI)
+        .map(|i| value.clone() - Expression::Constant(F::from(i as u64)))
+        .reduce(|expr, factor| expr * factor)
+        .unwrap();
+
+      Constraints::with_selector(q, [("lo <= value < hi", poly)])
+    });
+
+    IntervalConfig { value, q_check }
+  }
+
+  /// Witnesses `value` and checks it lies in `[LO, HI)`.
+  pub fn assign<V: Clone>(
+    &self,
+    mut layouter: impl Layouter<F>,
+    value: Value<V>
+  ) -> Result<AssignedCell<V, F>, Error>
+    where for<'v> Assigned<F>: From<&'v V>
+  {
+    layouter.assign_region(
+      || "interval check region",
+      |mut region| {
+        self.config.q_check.enable(&mut region, 0)?;
+        region.assign_advice(|| "value", self.config.value, 0, || value.clone())
+      }
+    )
+  }
+
+  /// Checks a cell assigned by another gadget lies in `[LO, HI)`.
+  pub fn check_cell<V: Clone>(
+    &self,
+    mut layouter: impl Layouter<F>,
+    cell: &AssignedCell<V, F>
+  ) -> Result<AssignedCell<V, F>, Error>
+    where for<'v> Assigned<F>: From<&'v V>
+  {
+    layouter.assign_region(
+      || "interval check region",
+      |mut region| {
+        self.config.q_check.enable(&mut region, 0)?;
+        cell.copy_advice(|| "value", &mut region, self.config.value, 0)
+      }
+    )
+  }
+}
+
+/// Proves `lo <= v < hi` for wide intervals. With `n` the smallest bit
+/// length such that `hi − lo <= 2^n`, both
+///
+///   `v − lo` and `v − lo + 2^n − (hi − lo)`
+///
+/// are range checked to `n` bits with a [`DecomposeChip`]. The first rules
+/// out `v < lo`, the second `v >= hi`. The offsets sit in a fixed column, so
+/// the bounds are part of the verifying key but not of the circuit's type.
+#[derive(Clone, Debug)]
+pub struct DecomposeIntervalConfig {
+  cells: Column<Advice>,
+  offsets: Column<Fixed>,
+  q_offsets: Selector,
+  decompose: DecomposeConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct DecomposeIntervalChip<F: PrimeField, const K: usize> {
+  config: DecomposeIntervalConfig,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField, const K: usize> DecomposeIntervalChip<F, K> {
+  pub fn construct(config: DecomposeIntervalConfig) -> Self {
+    Self {
+      config,
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>) -> DecomposeIntervalConfig {
+    let cells = meta.advice_column();
+    let offsets = meta.fixed_column();
+    let q_offsets = meta.selector();
+
+    meta.enable_equality(cells);
+
+    // Rows: value, value − lo, value − lo + 2^n − (hi − lo). Each offset row
+    // adds its fixed cell, `−lo` and then `2^n − (hi − lo)`, to the row above.
+    meta.create_gate("interval offset", |meta| {
+      let q = meta.query_selector(q_offsets);
+      let prev = meta.query_advice(cells, Rotation::prev());
+      let cur = meta.query_advice(cells, Rotation::cur());
+      let offset = meta.query_fixed(offsets);
+
+      Constraints::with_selector(q, [("cur = prev + offset", cur - (prev + offset))])
+    });
+
+    DecomposeIntervalConfig {
+      cells,
+      offsets,
+      q_offsets,
+      decompose: DecomposeChip::<F, K>::configure(meta),
+    }
+  }
+
+  /// Loads the decomposition word table. Call once per circuit.
+  pub fn load(&self, layouter: impl Layouter<F>) -> Result<(), Error> {
+    DecomposeChip::<F, K>::construct(self.config.decompose.clone()).load(layouter)
+  }
+
+  /// Bits needed for the offsets of `[lo, hi)`: the smallest `n` with
+  /// `hi − lo <= 2^n`.
+  pub fn num_bits(lo: u64, hi: u64) -> usize {
+    assert!(lo < hi, "the interval must not be empty");
+    (128 - ((hi - lo) as u128 - 1).leading_zeros()) as usize
+  }
+
+  /// Witnesses `value` and checks it lies in `[lo, hi)`.
+  pub fn assign(
+    &self,
+    mut layouter: impl Layouter<F>,
+    value: Value<F>,
+    lo: u64,
+    hi: u64
+  ) -> Result<AssignedCell<F, F>, Error> {
+    let value = layouter.assign_region(
+      || "interval value",
+      |mut region| region.assign_advice(|| "value", self.config.cells, 0, || value)
+    )?;
+    self.check_cell(layouter, &value, lo, hi)?;
+
+    Ok(value)
+  }
+
+  /// Checks a cell assigned by another gadget lies in `[lo, hi)`.
+  pub fn check_cell(
+    &self,
+    mut layouter: impl Layouter<F>,
+    cell: &AssignedCell<F, F>,
+    lo: u64,
+    hi: u64
+  ) -> Result<(), Error> {
+    let num_bits = Self::num_bits(lo, hi);
+    let slack = F::from_u128((1u128 << num_bits) - (hi - lo) as u128);
+    let lo = F::from(lo);
+
+    let (lower, upper) = layouter.assign_region(
+      || "interval offsets",
+      |mut region| {
+        let value = cell.copy_advice(|| "value", &mut region, self.config.cells, 0)?;
+
+        self.config.q_offsets.enable(&mut region, 1)?;
+        self.config.q_offsets.enable(&mut region, 2)?;
+        region.assign_fixed(|| "-lo", self.config.offsets, 1, || Value::known(-lo))?;
+        region.assign_fixed(|| "2^n - (hi - lo)", self.config.offsets, 2, || Value::known(slack))?;
+
+        let lower_value = value.value().map(|v| *v - lo);
+        let lower = region.assign_advice(|| "value - lo", self.config.cells, 1, || lower_value)?;
+        let upper = region.assign_advice(
+          || "value - lo + 2^n - (hi - lo)",
+          self.config.cells,
+          2,
+          || lower_value.map(|l| l + slack)
+        )?;
+
+        Ok((lower, upper))
+      }
+    )?;
+
+    let decompose = DecomposeChip::<F, K>::construct(self.config.decompose.clone());
+    decompose.copy_check(layouter.namespace(|| "lower bound"), &lower, num_bits, true)?;
+    decompose.copy_check(layouter.namespace(|| "upper bound"), &upper, num_bits, true)?;
+
+    Ok(())
+  }
+}
+
+#[derive(Default)]
+pub struct IntervalCircuit<F: PrimeField, const LO: usize, const HI: usize> {
+  assigned_value: Value<Assigned<F>>,
+}
+
+impl<F: PrimeField, const LO: usize, const HI: usize> IntervalCircuit<F, LO, HI> {
+  pub fn new(value: F) -> Self {
+    Self {
+      assigned_value: Value::known(value.into()),
+    }
+  }
+
+  pub fn instances(&self) -> Vec<Vec<F>> {
+    vec![]
+  }
+
+  /// `k` for the single checked row.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
+impl<F: PrimeField, const LO: usize, const HI: usize> Circuit<F> for IntervalCircuit<F, LO, HI> {
+  type Config = IntervalConfig;
+  type FloorPlanner = SimpleFloorPlanner;
+
+  fn without_witnesses(&self) -> Self {
+    Self::default()
+  }
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    IntervalChip::<F, LO, HI>::configure(meta)
+  }
+
+  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let chip = IntervalChip::<F, LO, HI>::construct(config);
+    chip.assign(layouter.namespace(|| "value"), self.assigned_value)?;
+
+    Ok(())
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct DecomposeIntervalCircuit<F: PrimeField, const K: usize> {
+  pub value: Value<F>,
+  pub lo: u64,
+  pub hi: u64,
+}
+
+impl<F: PrimeField, const K: usize> DecomposeIntervalCircuit<F, K> {
+  pub fn new(value: F, lo: u64, hi: u64) -> Self {
+    Self {
+      value: Value::known(value),
+      lo,
+      hi,
+    }
+  }
+
+  pub fn instances(&self) -> Vec<Vec<F>> {
+    vec![]
+  }
+
+  /// `k` for the word table and both decompositions.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
+impl<F: PrimeField, const K: usize> Circuit<F> for DecomposeIntervalCircuit<F, K> {
+  type Config = DecomposeIntervalConfig;
+  type FloorPlanner = SimpleFloorPlanner;
+
+  fn without_witnesses(&self) -> Self {
+    Self {
+      value: Value::unknown(),
+      ..self.clone()
+    }
+  }
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    DecomposeIntervalChip::<F, K>::configure(meta)
+  }
+
+  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let chip = DecomposeIntervalChip::<F, K>::construct(config);
+    chip.load(layouter.namespace(|| "table"))?;
+    chip.assign(layouter.namespace(|| "value"), self.value, self.lo, self.hi)?;
+
+    Ok(())
+  }
+}
+
+/// Reference for the interval circuits: whether `lo <= value < hi`.
+pub fn interval_check_native<F: PrimeField>(value: F, lo: u64, hi: u64) -> bool {
+  field_to_u128(value).is_some_and(|v| (lo as u128) <= v && v < hi as u128)
+}
+
+#[cfg(test)]
+mod tests {
+  use halo2_proofs::{ dev::MockProver, pasta::Fp };
+
+  use super::*;
+
+  fn check_poly<const LO: usize, const HI: usize>(value: Fp) -> bool {
+    let circuit = IntervalCircuit::<Fp, LO, HI>::new(value);
+    MockProver::run(circuit.k(), &circuit, circuit.instances()).unwrap().verify().is_ok()
+  }
+
+  fn check_decompose(value: Fp, lo: u64, hi: u64) -> bool {
+    let circuit = DecomposeIntervalCircuit::<Fp, 8>::new(value, lo, hi);
+    MockProver::run(circuit.k(), &circuit, circuit.instances()).unwrap().verify().is_ok()
+  }
+
+  #[test]
+  fn test_interval_poly_edges() {
+    assert!(!check_poly::<18, 26>(Fp::from(17)));
+    assert!(check_poly::<18, 26>(Fp::from(18)));
+    assert!(check_poly::<18, 26>(Fp::from(25)));
+    assert!(!check_poly::<18, 26>(Fp::from(26)));
+    assert!(!check_poly::<18, 26>(Fp::zero()));
+    assert!(!check_poly::<18, 26>(-Fp::from(18)));
+  }
+
+  #[test]
+  fn test_interval_poly_matches_native() {
+    for v in 0..40 {
+      let value = Fp::from(v);
+      assert_eq!(check_poly::<5, 12>(value), interval_check_native(value, 5, 12));
+    }
+  }
+
+  #[test]
+  fn test_interval_decompose_edges() {
+    let (lo, hi) = (18, 120);
+    assert!(!check_decompose(Fp::from(lo - 1), lo, hi));
+    assert!(check_decompose(Fp::from(lo), lo, hi));
+    assert!(check_decompose(Fp::from(hi - 1), lo, hi));
+    assert!(!check_decompose(Fp::from(hi), lo, hi));
+    assert!(!check_decompose(-Fp::one(), lo, hi));
+
+    // 1000 <= amount <= 10^9
+    let (lo, hi) = (1000, 1_000_000_001);
+    assert!(!check_decompose(Fp::from(999), lo, hi));
+    assert!(check_decompose(Fp::from(1000), lo, hi));
+    assert!(check_decompose(Fp::from(1_000_000_000), lo, hi));
+    assert!(!check_decompose(Fp::from(1_000_000_001), lo, hi));
+  }
+
+  #[test]
+  fn test_interval_decompose_extremes() {
+    // A power-of-two width leaves no slack on the upper offset.
+    assert!(check_decompose(Fp::from(511), 256, 512));
+    assert!(!check_decompose(Fp::from(512), 256, 512));
+
+    // A single-value interval needs zero bits.
+    assert!(check_decompose(Fp::from(7), 7, 8));
+    assert!(!check_decompose(Fp::from(8), 7, 8));
+    assert!(!check_decompose(Fp::from(6), 7, 8));
+
+    assert!(check_decompose(Fp::from(u64::MAX - 1), 0, u64::MAX));
+    assert!(!check_decompose(Fp::from(u64::MAX), 0, u64::MAX));
+  }
+
+  #[test]
+  fn test_interval_num_bits() {
+    assert_eq!(DecomposeIntervalChip::<Fp, 8>::num_bits(7, 8), 0);
+    assert_eq!(DecomposeIntervalChip::<Fp, 8>::num_bits(0, 2), 1);
+    assert_eq!(DecomposeIntervalChip::<Fp, 8>::num_bits(256, 512), 8);
+    assert_eq!(DecomposeIntervalChip::<Fp, 8>::num_bits(256, 513), 9);
+    assert_eq!(DecomposeIntervalChip::<Fp, 8>::num_bits(0, u64::MAX), 64);
+  }
+}
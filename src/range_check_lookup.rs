@@ -0,0 +1,161 @@
+use halo2_proofs::{
+  plonk::{ Assigned, Circuit, ConstraintSystem, Error },
+  circuit::*,
+};
+
+use group::ff::PrimeField;
+
+use std::marker::PhantomData;
+
+use crate::{
+  range_check::{ RangeChip, RangeConfig, RangeStrategy },
+  stats::k_for,
+};
+
+/// Range check through a lookup into a table holding `0..RANGE`. Unlike the
+/// product-polynomial gate the degree stays constant; the cost is a table
+/// of `RANGE` rows.
+///
+/// This is [`RangeChip`] pinned to [`RangeStrategy::Lookup`], for callers
+/// that want the table whatever `RANGE` is.
+#[derive(Clone, Debug)]
+pub struct RangeLookupConfig {
+  range: RangeConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct RangeLookupChip<F: PrimeField, const RANGE: usize> {
+  range: RangeChip<F, RANGE>,
+}
+
+impl<F: PrimeField, const RANGE: usize> RangeLookupChip<F, RANGE> {
+  pub fn construct(config: RangeLookupConfig) -> Self {
+    Self { range: RangeChip::construct(config.range) }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>) -> RangeLookupConfig {
+    // No product polynomial fits a zero degree budget.
+    let range = RangeChip::<F, RANGE>::configure_with_max_degree(meta, 0);
+    debug_assert_eq!(range.strategy(), RangeStrategy::Lookup);
+    RangeLookupConfig { range }
+  }
+
+  /// Loads `0..RANGE` into the table column. Call once per circuit.
+  pub fn load(&self, layouter: impl Layouter<F>) -> Result<(), Error> {
+    self.range.load(layouter)
+  }
+
+  /// Witnesses `value` and range checks it; see [`RangeChip::assign`].
+  pub fn assign<V: Clone>(&self, layouter: impl Layouter<F>, value: Value<V>) -> Result<AssignedCell<V, F>, Error>
+    where for<'v> Assigned<F>: From<&'v V>
+  {
+    self.range.assign(layouter, value)
+  }
+
+  /// Range checks a cell assigned by another gadget; see
+  /// [`RangeChip::check_cell`].
+  pub fn check_cell<V: Clone>(
+    &self,
+    layouter: impl Layouter<F>,
+    cell: &AssignedCell<V, F>
+  ) -> Result<AssignedCell<V, F>, Error>
+    where for<'v> Assigned<F>: From<&'v V>
+  {
+    self.range.check_cell(layouter, cell)
+  }
+}
+
+#[derive(Default)]
+pub struct RangeLookupCircuit<F: PrimeField, const RANGE: usize> {
+  assigned_value: Value<Assigned<F>>,
+  _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField, const RANGE: usize> RangeLookupCircuit<F, RANGE> {
+  pub fn new(value: F) -> Self {
+    Self {
+      assigned_value: Value::known(value.into()),
+      _marker: PhantomData,
+    }
+  }
+
+  pub fn instances(&self) -> Vec<Vec<F>> {
+    vec![]
+  }
+
+  /// `k` for the `RANGE`-row table.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
+impl<F: PrimeField, const RANGE: usize> Circuit<F> for RangeLookupCircuit<F, RANGE> {
+  type Config = RangeLookupConfig;
+  type FloorPlanner = SimpleFloorPlanner;
+
+  fn without_witnesses(&self) -> Self {
+    Self::default()
+  }
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    RangeLookupChip::<F, RANGE>::configure(meta)
+  }
+
+  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let chip = RangeLookupChip::<F, RANGE>::construct(config);
+    chip.load(layouter.namespace(|| "table"))?;
+    chip.assign(
+      layouter.namespace(|| "value"),
+      self.assigned_value
+    )?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use halo2_proofs::{ dev::{ FailureLocation, MockProver, VerifyFailure }, pasta::Fp };
+
+  use super::*;
+
+  fn check<const RANGE: usize>(value: u64) -> Result<(), Vec<VerifyFailure>> {
+    let circuit = RangeLookupCircuit::<Fp, RANGE>::new(Fp::from(value));
+    MockProver::run(circuit.k(), &circuit, circuit.instances()).unwrap().verify()
+  }
+
+  #[test]
+  fn test_range_lookup_small() {
+    const RANGE: usize = 8;
+    for i in 0..RANGE as u64 {
+      assert_eq!(check::<RANGE>(i), Ok(()));
+    }
+    assert!(check::<RANGE>(RANGE as u64).is_err());
+  }
+
+  #[test]
+  fn test_range_lookup_256() {
+    const RANGE: usize = 256;
+    assert_eq!(check::<RANGE>(0), Ok(()));
+    assert_eq!(check::<RANGE>(255), Ok(()));
+    assert_eq!(
+      check::<RANGE>(256),
+      Err(
+        vec![VerifyFailure::Lookup {
+          lookup_index: 0,
+          location: FailureLocation::InRegion {
+            region: (1, "range check region").into(),
+            offset: 0,
+          },
+        }]
+      )
+    );
+  }
+
+  #[test]
+  fn test_range_lookup_2_16() {
+    const RANGE: usize = 1 << 16;
+    assert_eq!(check::<RANGE>(RANGE as u64 - 1), Ok(()));
+    assert!(check::<RANGE>(RANGE as u64).is_err());
+  }
+}
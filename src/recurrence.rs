@@ -0,0 +1,363 @@
+use std::{ fmt::Debug, marker::PhantomData };
+use halo2_proofs::{ circuit::*, plonk::*, poly::Rotation };
+
+use group::ff::PrimeField;
+
+use crate::stats::k_for;
+
+/// A linear recurrence `a_n = Σ c_i · a_{n-i}` with fixed coefficients,
+/// listed as `COEFFS[i - 1] = c_i`. Its order is `COEFFS.len()`.
+pub trait Recurrence: Clone + Debug {
+  const COEFFS: &'static [i64];
+
+  fn coefficients<F: PrimeField>() -> Vec<F> {
+    Self::COEFFS
+      .iter()
+      .map(|&c| {
+        let abs = F::from(c.unsigned_abs());
+        if c < 0 { -abs } else { abs }
+      })
+      .collect()
+  }
+}
+
+/// `a_n = a_{n-1} + a_{n-2}`. Seeded with `(0, 1)` or `(1, 1)` this is the
+/// Fibonacci sequence, seeded with `(2, 1)` it gives the Lucas numbers.
+#[derive(Clone, Debug)]
+pub struct Fibonacci;
+
+impl Recurrence for Fibonacci {
+  const COEFFS: &'static [i64] = &[1, 1];
+}
+
+/// `a_n = 2 · a_{n-1} + a_{n-2}`, the Pell numbers when seeded with `(0, 1)`.
+#[derive(Clone, Debug)]
+pub struct Pell;
+
+impl Recurrence for Pell {
+  const COEFFS: &'static [i64] = &[2, 1];
+}
+
+/// `a_n = a_{n-1} + a_{n-2} + a_{n-3}`.
+#[derive(Clone, Debug)]
+pub struct Tribonacci;
+
+impl Recurrence for Tribonacci {
+  const COEFFS: &'static [i64] = &[1, 1, 1];
+}
+
+/// The coefficients are baked into the gate as constants, so they are part
+/// of the verifying key.
+#[derive(Clone, Debug)]
+pub struct RecurrenceConfig<F: PrimeField> {
+  advice: Column<Advice>,
+  selector: Selector,
+  instance: Column<Instance>,
+  coeffs: Vec<F>,
+}
+
+impl<F: PrimeField> RecurrenceConfig<F> {
+  pub fn order(&self) -> usize {
+    self.coeffs.len()
+  }
+}
+
+/// The seed cells and the cell holding `a_n`.
+type Sequence<F> = (Vec<AssignedCell<F, F>>, AssignedCell<F, F>);
+
+/// Lays out `a_0..=a_n` down one advice column, with the recurrence as a
+/// rotation gate.
+///
+/// [`crate::fibonacci::FibonacciChip`] is not rebuilt on top of this: its
+/// three-column, region-per-step layout is the copy-constraint baseline the
+/// other Fibonacci chips are measured against, and
+/// [`crate::fibonacci_bounded::BoundedFiboCircuit`] shares those columns.
+/// The order-2 case of this layout is
+/// [`crate::fibonacci_rotation::FibonacciRotationChip`], which adds the
+/// [`SeedMode`](crate::fibonacci::SeedMode) handling
+/// [`crate::fibonacci::FiboCircuit`] needs.
+#[derive(Debug, Clone)]
+pub struct RecurrenceChip<F: PrimeField> {
+  config: RecurrenceConfig<F>,
+}
+
+impl<F: PrimeField> RecurrenceChip<F> {
+  pub fn construct(config: RecurrenceConfig<F>) -> Self {
+    Self { config }
+  }
+
+  pub fn configure(meta: &mut ConstraintSystem<F>, coeffs: &[F]) -> RecurrenceConfig<F> {
+    assert!(!coeffs.is_empty(), "a recurrence needs at least one coefficient");
+
+    let advice = meta.advice_column();
+    let selector = meta.selector();
+    let instance = meta.instance_column();
+    let constant = meta.fixed_column();
+
+    meta.enable_equality(advice);
+    meta.enable_equality(instance);
+    meta.enable_constant(constant);
+
+    let order = coeffs.len();
+    meta.create_gate("recurrence", |meta| {
+      let s = meta.query_selector(selector);
+      let next = meta.query_advice(advice, Rotation(order as i32));
+      let sum = coeffs
+        .iter()
+        .enumerate()
+        .fold(Expression::Constant(F::ZERO), |sum, (i, c)| {
+          let prev = meta.query_advice(advice, Rotation((order - 1 - i) as i32));
+          sum + Expression::Constant(*c) * prev
+        });
+      vec![s * (next - sum)]
+    });
+
+    RecurrenceConfig {
+      advice,
+      selector,
+      instance,
+      coeffs: coeffs.to_vec(),
+    }
+  }
+
+  /// Witnesses the seeds `a_0..a_{order-1}` and assigns the sequence up to
+  /// `a_n` in one region. Returns the seed cells and the cell holding `a_n`.
+  pub fn assign(
+    &self,
+    mut layouter: impl Layouter<F>,
+    seeds: &[Value<F>],
+    n: usize
+  ) -> Result<Sequence<F>, Error> {
+    let order = self.config.order();
+    assert_eq!(seeds.len(), order, "one seed per coefficient");
+    assert!(n >= order, "n must be past the seeds");
+
+    layouter.assign_region(
+      || "sequence",
+      |mut region| {
+        let seed_cells = seeds
+          .iter()
+          .enumerate()
+          .map(|(row, seed)| {
+            region.assign_advice(|| format!("a({})", row), self.config.advice, row, || *seed)
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+
+        let mut window: Vec<Value<F>> = seeds.to_vec();
+        let mut last = seed_cells[order - 1].clone();
+        for row in order..=n {
+          self.config.selector.enable(&mut region, row - order)?;
+
+          let value = self.config.coeffs
+            .iter()
+            .enumerate()
+            .fold(Value::known(F::ZERO), |sum, (i, c)| {
+              sum + window[order - 1 - i].map(|prev| prev * c)
+            });
+
+          last = region.assign_advice(|| format!("a({})", row), self.config.advice, row, || value)?;
+          window.remove(0);
+          window.push(value);
+        }
+
+        Ok((seed_cells, last))
+      }
+    )
+  }
+
+  /// Fixes the sequence index `n` as a constant so it can be exposed as a
+  /// public input next to `a_n`.
+  pub fn assign_index(
+    &self,
+    mut layouter: impl Layouter<F>,
+    n: usize
+  ) -> Result<AssignedCell<F, F>, Error> {
+    layouter.assign_region(
+      || "index",
+      |mut region| {
+        region.assign_advice_from_constant(
+          || "n",
+          self.config.advice,
+          0,
+          F::from(n as u64)
+        )
+      }
+    )
+  }
+
+  pub fn expose_public(
+    &self,
+    mut layouter: impl Layouter<F>,
+    cell: &AssignedCell<F, F>,
+    row: usize
+  ) -> Result<(), Error> {
+    layouter.constrain_instance(cell.cell(), self.config.instance, row)
+  }
+}
+
+/// Proves `a_n` of the recurrence `R`. Public inputs are
+/// `[a_0, .., a_{order-1}, a_n, n]`.
+#[derive(Clone, Debug)]
+pub struct RecurrenceCircuit<F: PrimeField, R: Recurrence> {
+  pub n: usize,
+  pub seeds: Vec<Value<F>>,
+  _marker: PhantomData<R>,
+}
+
+impl<F: PrimeField, R: Recurrence> RecurrenceCircuit<F, R> {
+  /// Panics unless there is one seed per coefficient and `n` is past them,
+  /// rather than later in `synthesize`.
+  pub fn new(n: usize, seeds: Vec<Value<F>>) -> Self {
+    assert_eq!(seeds.len(), R::COEFFS.len(), "one seed per coefficient");
+    assert!(n >= R::COEFFS.len(), "n must be past the seeds");
+    Self {
+      n,
+      seeds,
+      _marker: PhantomData,
+    }
+  }
+
+  /// Instance row holding `a_n`; `n` sits on the row after it.
+  pub fn out_row() -> usize {
+    R::COEFFS.len()
+  }
+
+  /// Rows used by the circuit: `a_0..=a_n` plus the index row.
+  pub fn rows(&self) -> usize {
+    self.n + 2
+  }
+
+  /// `k` for the sequence and its public rows.
+  pub fn k(&self) -> u32 {
+    k_for(self)
+  }
+}
+
+impl<F: PrimeField, R: Recurrence> Circuit<F> for RecurrenceCircuit<F, R> {
+  type Config = RecurrenceConfig<F>;
+  type FloorPlanner = SimpleFloorPlanner;
+
+  fn without_witnesses(&self) -> Self {
+    Self::new(self.n, vec![Value::unknown(); self.seeds.len()])
+  }
+
+  fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+    RecurrenceChip::configure(meta, &R::coefficients::<F>())
+  }
+
+  fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+    let chip = RecurrenceChip::construct(config);
+
+    let (seeds, out) = chip.assign(layouter.namespace(|| "sequence"), &self.seeds, self.n)?;
+    for (row, seed) in seeds.iter().enumerate() {
+      chip.expose_public(layouter.namespace(|| "seed"), seed, row)?;
+    }
+    chip.expose_public(layouter.namespace(|| "out"), &out, Self::out_row())?;
+
+    let index = chip.assign_index(layouter.namespace(|| "index"), self.n)?;
+    chip.expose_public(layouter.namespace(|| "n"), &index, Self::out_row() + 1)?;
+
+    Ok(())
+  }
+}
+
+/// Reference `a_n` of the recurrence `R` computed outside the circuit.
+pub fn recurrence_native<F: PrimeField, R: Recurrence>(seeds: &[F], n: usize) -> F {
+  let coeffs = R::coefficients::<F>();
+  assert_eq!(seeds.len(), coeffs.len(), "one seed per coefficient");
+
+  let mut window = seeds.to_vec();
+  for _ in 0..n {
+    let next = coeffs
+      .iter()
+      .enumerate()
+      .fold(F::ZERO, |sum, (i, c)| sum + window[window.len() - 1 - i] * c);
+    window.remove(0);
+    window.push(next);
+  }
+  window[0]
+}
+
+/// Instance columns [`RecurrenceCircuit`] expects for `seeds` and length `n`.
+pub fn recurrence_instances<F: PrimeField, R: Recurrence>(seeds: &[F], n: usize) -> Vec<Vec<F>> {
+  let mut instance = seeds.to_vec();
+  instance.push(recurrence_native::<F, R>(seeds, n));
+  instance.push(F::from(n as u64));
+  vec![instance]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use halo2_proofs::{ dev::MockProver, pasta::Fp };
+
+  fn check<R: Recurrence>(seeds: &[u64], n: usize, expected: u64) {
+    let seeds: Vec<Fp> = seeds.iter().map(|&s| Fp::from(s)).collect();
+    assert_eq!(recurrence_native::<Fp, R>(&seeds, n), Fp::from(expected));
+
+    let seed_values = seeds.iter().map(|&s| Value::known(s)).collect();
+    let circuit = RecurrenceCircuit::<Fp, R>::new(n, seed_values);
+
+    let mut instances = recurrence_instances::<Fp, R>(&seeds, n);
+    let prover = MockProver::run(circuit.k(), &circuit, instances.clone()).unwrap();
+    prover.assert_satisfied();
+
+    let out_row = RecurrenceCircuit::<Fp, R>::out_row();
+    instances[0][out_row] += Fp::one();
+    let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+    assert!(prover.verify().is_err());
+  }
+
+  #[test]
+  fn recurrence_fibonacci() {
+    check::<Fibonacci>(&[1, 1], 9, 55);
+    check::<Fibonacci>(&[0, 1], 30, 832040);
+  }
+
+  #[test]
+  fn recurrence_lucas() {
+    check::<Fibonacci>(&[2, 1], 10, 123);
+  }
+
+  #[test]
+  fn recurrence_pell() {
+    check::<Pell>(&[0, 1], 10, 2378);
+  }
+
+  #[test]
+  fn recurrence_tribonacci() {
+    check::<Tribonacci>(&[0, 0, 1], 10, 81);
+  }
+
+  #[test]
+  fn recurrence_native_matches_circuit() {
+    for n in 3..25 {
+      let seeds = [Fp::from(n as u64), Fp::from(3), -Fp::from(5)];
+      let seed_values = seeds.iter().map(|&s| Value::known(s)).collect();
+      let circuit = RecurrenceCircuit::<Fp, Tribonacci>::new(n, seed_values);
+
+      let instances = recurrence_instances::<Fp, Tribonacci>(&seeds, n);
+      let prover = MockProver::run(circuit.k(), &circuit, instances).unwrap();
+      prover.assert_satisfied();
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "n must be past the seeds")]
+  fn recurrence_rejects_short_sequences() {
+    RecurrenceCircuit::<Fp, Tribonacci>::new(2, vec![Value::known(Fp::one()); 3]);
+  }
+
+  #[derive(Clone, Debug)]
+  struct Mersenne;
+
+  impl Recurrence for Mersenne {
+    const COEFFS: &'static [i64] = &[3, -2];
+  }
+
+  #[test]
+  fn recurrence_negative_coefficient() {
+    // a_n = 3a_{n-1} - 2a_{n-2} with seeds (0, 1) gives 2^n - 1.
+    check::<Mersenne>(&[0, 1], 20, (1 << 20) - 1);
+  }
+}
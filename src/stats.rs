@@ -0,0 +1,519 @@
+//! Circuit shape, row usage and proof cost, and the smallest `k` a circuit
+//! fits in.
+//!
+//! Row usage comes from a dry synthesis that records which rows the floor
+//! planner touches without evaluating any witness, so it works on
+//! `without_witnesses()` circuits too. The shape comes from the public
+//! `ConstraintSystem` and `CircuitGates` APIs, and the proof and verifier
+//! figures from `halo2_proofs`' `CircuitCost`.
+
+use std::{ collections::{ HashMap, HashSet }, fmt };
+
+use group::ff::Field;
+use halo2_proofs::{
+  circuit::Value,
+  dev::{ CircuitCost, CircuitGates, MockProver, VerifyFailure },
+  pasta::{ Eq, Fp },
+  plonk::{
+    Advice,
+    Any,
+    Assigned,
+    Assignment,
+    Circuit,
+    Column,
+    ConstraintSystem,
+    Error,
+    Fixed,
+    FloorPlanner,
+    Instance,
+    Selector,
+  },
+};
+use serde::Serialize;
+
+/// Pasta's two-adicity caps the evaluation domain at `2^32` rows.
+const MAX_K: u32 = 32;
+
+#[derive(Debug)]
+pub enum StatsError {
+  Synthesis(Error),
+  /// The circuit fits in `2^k` rows but its constraints do not hold; more
+  /// rows would not change that.
+  Unsatisfied { k: u32, failures: Vec<VerifyFailure> },
+  /// A `CircuitCost` field was missing from its `Debug` output, so the
+  /// pinned `halo2_proofs` version was changed.
+  CostFormat { field: &'static str },
+}
+
+impl fmt::Display for StatsError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      StatsError::Synthesis(err) => write!(f, "synthesis failed: {:?}", err),
+      StatsError::Unsatisfied { k, failures } =>
+        write!(f, "{} constraint failures at k = {}", failures.len(), k),
+      StatsError::CostFormat { field } => write!(f, "CircuitCost has no `{}` field", field),
+    }
+  }
+}
+
+impl std::error::Error for StatsError {}
+
+impl From<Error> for StatsError {
+  fn from(err: Error) -> Self {
+    StatsError::Synthesis(err)
+  }
+}
+
+/// The requirement that sets a circuit's minimal `k`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bound {
+  /// Rows assigned in regions: advice, fixed and selector cells.
+  Rows,
+  /// A lookup table loaded with `assign_table`.
+  LookupTable,
+  /// Public inputs, whether passed in or referenced by the circuit.
+  Instances,
+  /// The circuit is so small that `ConstraintSystem::minimum_rows` decides.
+  BlindingFactors,
+}
+
+/// Rows a circuit uses, independent of `k`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RowUsage {
+  /// One past the last row assigned outside lookup tables.
+  pub rows: usize,
+  /// The longest lookup table.
+  pub table_rows: usize,
+  /// One past the last instance row queried, copied or passed in.
+  pub instance_rows: usize,
+  /// Rows reserved at the end of every column to blind the witness.
+  pub blinding_factors: usize,
+}
+
+impl RowUsage {
+  /// Configures `C` and dry-synthesizes `circuit`, counting
+  /// `instance_lengths` as used instance rows.
+  pub fn measure<F: Field, C: Circuit<F>>(circuit: &C, instance_lengths: &[usize]) -> Result<Self, Error> {
+    let mut meta = ConstraintSystem::<F>::default();
+    let config = C::configure(&mut meta);
+
+    // The columns enabled for constants are private, so the floor planner
+    // gets a spare one. Constant columns hold nothing else, so it fills the
+    // same rows.
+    let constants = vec![meta.clone().fixed_column()];
+    let mut dry_run = DryRun::default();
+    C::FloorPlanner::synthesize(&mut dry_run, circuit, config, constants)?;
+
+    let instance_rows = instance_lengths.iter().copied().fold(dry_run.instance_rows, usize::max);
+    let rows = dry_run.fixed_rows
+      .iter()
+      .filter(|(column, _)| !dry_run.tables.contains(column))
+      .map(|(_, rows)| *rows)
+      .fold(dry_run.rows, usize::max);
+
+    Ok(Self {
+      rows,
+      table_rows: dry_run.table_rows,
+      instance_rows,
+      blinding_factors: meta.blinding_factors(),
+    })
+  }
+
+  /// Rows needed for each requirement: its own rows, then the blinding rows
+  /// and the `l_last` row. Ties go to the earlier entry.
+  fn requirements(&self) -> [(Bound, usize); 4] {
+    let reserved = self.blinding_factors + 1;
+    [
+      (Bound::Rows, self.rows + reserved),
+      (Bound::LookupTable, self.table_rows + reserved),
+      (Bound::Instances, self.instance_rows + reserved),
+      // `ConstraintSystem::minimum_rows`
+      (Bound::BlindingFactors, self.blinding_factors + 3),
+    ]
+  }
+
+  /// The requirement with the most rows.
+  pub fn bound(&self) -> Bound {
+    let mut requirements = self.requirements().into_iter();
+    let first = requirements.next().unwrap();
+    requirements.fold(first, |max, next| if next.1 > max.1 { next } else { max }).0
+  }
+
+  /// Rows needed in total; `2^k` must be at least this.
+  pub fn needed(&self) -> usize {
+    self.requirements().iter().map(|(_, rows)| *rows).max().unwrap()
+  }
+
+  /// The smallest `k` with `2^k >= self.needed()`.
+  pub fn k(&self) -> u32 {
+    let needed = self.needed();
+    let mut k = 1;
+    while (1 << k) < needed {
+      k += 1;
+    }
+    k
+  }
+}
+
+/// The smallest `k` that fits `circuit`'s rows, counting only the instance
+/// rows it references. Panics if it does not synthesize.
+pub fn k_for<F: Field, C: Circuit<F>>(circuit: &C) -> u32 {
+  RowUsage::measure(circuit, &[]).expect("circuit synthesizes in a dry run").k()
+}
+
+/// The smallest `k` a circuit is satisfied at, and why it is not smaller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct MinimalK {
+  pub k: u32,
+  pub bound: Bound,
+  pub usage: RowUsage,
+}
+
+/// Finds the smallest `k` for which `circuit` synthesizes and `MockProver`
+/// is satisfied with `instances`.
+///
+/// The estimate from [`RowUsage`] is confirmed with `MockProver`, and `k` is
+/// only raised further if the prover still runs out of rows.
+pub fn minimal_k<F: Field + Ord, C: Circuit<F>>(circuit: &C, instances: &[Vec<F>]) -> Result<MinimalK, StatsError> {
+  let lengths: Vec<usize> = instances.iter().map(Vec::len).collect();
+  let usage = RowUsage::measure(circuit, &lengths)?;
+
+  let mut k = usage.k();
+  loop {
+    match MockProver::run(k, circuit, instances.to_vec()) {
+      Ok(prover) =>
+        return match prover.verify() {
+          Ok(()) => Ok(MinimalK { k, bound: usage.bound(), usage }),
+          Err(failures) => Err(StatsError::Unsatisfied { k, failures }),
+        },
+      Err(Error::NotEnoughRowsAvailable { .. } | Error::InstanceTooLarge) if k < MAX_K => {
+        k += 1;
+      }
+      Err(err) => {
+        return Err(err.into());
+      }
+    }
+  }
+}
+
+/// Bytes of a proof for one instance of the circuit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ProofCost {
+  pub bytes: usize,
+  /// Bytes each additional instance in the same proof adds.
+  pub marginal_bytes: usize,
+  pub commitments: usize,
+  pub evaluations: usize,
+}
+
+/// Rough verifier work for one proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct VerifierCost {
+  /// Distinct rotation sets opened by the multiopen argument.
+  pub point_sets: usize,
+  /// Scalars the verifier reads from the proof.
+  pub evaluations: usize,
+  /// Points in the final multiscalar multiplication: the proof and
+  /// verifying key commitments, plus the `2^k` generators the IPA check
+  /// recomputes.
+  pub msm_size: usize,
+}
+
+/// A circuit's shape and costs at its minimal `k`.
+///
+/// Column counts are as configured; `halo2_proofs` compresses the
+/// selectors into extra fixed columns before proving, which the proof and
+/// verifier figures include.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct CircuitStats {
+  pub k: u32,
+  pub bound: Bound,
+  pub advice_columns: usize,
+  /// Including lookup table columns.
+  pub fixed_columns: usize,
+  pub instance_columns: usize,
+  pub selectors: usize,
+  /// Gate polynomials; a gate with several constraints counts each.
+  pub constraints: usize,
+  /// Before selector compression, which can raise it.
+  pub max_degree: usize,
+  pub lookups: usize,
+  pub permutation_columns: usize,
+  pub usage: RowUsage,
+  pub proof: ProofCost,
+  pub verifier: VerifierCost,
+}
+
+impl CircuitStats {
+  /// Measures `circuit` at its [`minimal_k`].
+  pub fn measure<C: Circuit<Fp> + fmt::Debug>(circuit: &C, instances: &[Vec<Fp>]) -> Result<Self, StatsError> {
+    let MinimalK { k, bound, usage } = minimal_k(circuit, instances)?;
+
+    let mut meta = ConstraintSystem::<Fp>::default();
+    C::configure(&mut meta);
+
+    // One line per constraint under a header.
+    let constraints = CircuitGates::collect::<Fp, C>().queries_to_csv().lines().count() - 1;
+
+    // `CircuitCost` and its `ProofSize` keep these counts in private fields,
+    // so they are read from the `Debug` output. That format is why
+    // `halo2_proofs` is pinned to an exact version; a missing field fails
+    // with `StatsError::CostFormat` rather than reporting a guess.
+    let cost = CircuitCost::<Eq, C>::measure(k, circuit);
+    let commitments = cost_fields(&cost.proof_size(1), "commitments")?.iter().sum();
+    let evaluations = cost_fields(&cost.proof_size(1), "evaluations")?.iter().sum();
+    let compressed_fixed = cost_field(&cost, "num_fixed_columns")?;
+    let permutation_columns = cost_field(&cost, "permutation_cols")?;
+
+    // `ConstraintSystem` has no column or selector counts, and their indices
+    // are private. This relies on the pinned version allocating indices in
+    // order; see `count_allocated`.
+    Ok(Self {
+      k,
+      bound,
+      advice_columns: count_allocated(meta.clone().advice_column(), ConstraintSystem::advice_column),
+      fixed_columns: count_allocated(meta.clone().fixed_column(), ConstraintSystem::fixed_column),
+      instance_columns: count_allocated(meta.clone().instance_column(), ConstraintSystem::instance_column),
+      selectors: count_allocated(meta.clone().selector(), ConstraintSystem::selector),
+      constraints,
+      max_degree: meta.degree(),
+      lookups: cost_field(&cost, "lookups")?,
+      permutation_columns,
+      usage,
+      proof: ProofCost {
+        bytes: cost.proof_size(1).into(),
+        marginal_bytes: cost.marginal_proof_size().into(),
+        commitments,
+        evaluations,
+      },
+      verifier: VerifierCost {
+        point_sets: cost_field(&cost, "point_sets")?,
+        evaluations,
+        msm_size: commitments + compressed_fixed + permutation_columns + (1 << k),
+      },
+    })
+  }
+
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).expect("stats serialize to JSON")
+  }
+}
+
+/// How many columns or selectors of a kind a system has, given the next
+/// one it would allocate. Their indices are private, but they compare by
+/// index, so a fresh system allocates until it produces the same one. This
+/// assumes indices are handed out in order from zero, as they are in the
+/// pinned `halo2_proofs`.
+fn count_allocated<T: PartialEq>(next: T, allocate: impl Fn(&mut ConstraintSystem<Fp>) -> T) -> usize {
+  let mut scratch = ConstraintSystem::<Fp>::default();
+  let mut count = 0;
+  while allocate(&mut scratch) != next {
+    count += 1;
+  }
+  count
+}
+
+/// Every `usize` field called `name` in the `Debug` output of a
+/// `halo2_proofs` cost type, whose fields are private. Fails if there is no
+/// such field or one of them is not a number.
+fn cost_fields(cost: &impl fmt::Debug, name: &'static str) -> Result<Vec<usize>, StatsError> {
+  let debug = format!("{:?}", cost);
+  let key = format!("{}: ", name);
+  let fields: Option<Vec<usize>> = debug
+    .match_indices(&key)
+    .map(|(start, _)| debug[start + key.len()..].split(|c: char| !c.is_ascii_digit()).next()?.parse().ok())
+    .collect();
+  fields.filter(|fields| !fields.is_empty()).ok_or(StatsError::CostFormat { field: name })
+}
+
+/// The first field of [`cost_fields`].
+fn cost_field(cost: &impl fmt::Debug, name: &'static str) -> Result<usize, StatsError> {
+  Ok(cost_fields(cost, name)?[0])
+}
+
+/// An `Assignment` that only records which rows are used.
+#[derive(Default)]
+struct DryRun {
+  rows: usize,
+  fixed_rows: HashMap<Column<Fixed>, usize>,
+  /// Fixed columns filled to the end by `assign_table`.
+  tables: HashSet<Column<Fixed>>,
+  table_rows: usize,
+  instance_rows: usize,
+}
+
+impl DryRun {
+  fn use_row(&mut self, row: usize) {
+    self.rows = self.rows.max(row + 1);
+  }
+
+  fn use_cell(&mut self, column: Column<Any>, row: usize) {
+    match column.column_type() {
+      Any::Advice => self.use_row(row),
+      Any::Fixed => {
+        let rows = self.fixed_rows.entry(Column::<Fixed>::try_from(column).unwrap()).or_default();
+        *rows = (*rows).max(row + 1);
+      }
+      Any::Instance => {
+        self.instance_rows = self.instance_rows.max(row + 1);
+      }
+    }
+  }
+}
+
+impl<F: Field> Assignment<F> for DryRun {
+  fn enter_region<NR, N>(&mut self, _: N) where NR: Into<String>, N: FnOnce() -> NR {}
+
+  fn exit_region(&mut self) {}
+
+  fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+    where A: FnOnce() -> AR, AR: Into<String>
+  {
+    self.use_row(row);
+    Ok(())
+  }
+
+  fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+    // Instance rows are counted from the instances passed in and from the
+    // copies that reference them.
+    Ok(Value::unknown())
+  }
+
+  fn assign_advice<V, VR, A, AR>(&mut self, _: A, column: Column<Advice>, row: usize, _: V) -> Result<(), Error>
+    where V: FnOnce() -> Value<VR>, VR: Into<Assigned<F>>, A: FnOnce() -> AR, AR: Into<String>
+  {
+    self.use_cell(column.into(), row);
+    Ok(())
+  }
+
+  fn assign_fixed<V, VR, A, AR>(&mut self, _: A, column: Column<Fixed>, row: usize, _: V) -> Result<(), Error>
+    where V: FnOnce() -> Value<VR>, VR: Into<Assigned<F>>, A: FnOnce() -> AR, AR: Into<String>
+  {
+    self.use_cell(column.into(), row);
+    Ok(())
+  }
+
+  fn copy(&mut self, left: Column<Any>, left_row: usize, right: Column<Any>, right_row: usize) -> Result<(), Error> {
+    self.use_cell(left, left_row);
+    self.use_cell(right, right_row);
+    Ok(())
+  }
+
+  fn fill_from_row(&mut self, column: Column<Fixed>, row: usize, _: Value<Assigned<F>>) -> Result<(), Error> {
+    // The floor planners only fill table columns, from one past the table.
+    self.tables.insert(column);
+    self.table_rows = self.table_rows.max(row);
+    Ok(())
+  }
+
+  fn push_namespace<NR, N>(&mut self, _: N) where NR: Into<String>, N: FnOnce() -> NR {}
+
+  fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fibonacci::{ fibonacci_instances, FiboCircuit, SeedMode },
+    fibonacci_fast::FastFiboCircuit,
+    fibonacci_rotation::FiboRotationCircuit,
+    range_check::RangeCircuit,
+  };
+
+  #[test]
+  fn minimal_k_matches_helpers() {
+    for n in [2, 9, 20, 100, 300] {
+      let circuit = FiboCircuit::<Fp>::new(n);
+      let minimal = minimal_k(&circuit, &circuit.instances(Fp::one(), Fp::one())).unwrap();
+      assert_eq!(minimal.k, circuit.k());
+      assert_eq!(minimal.usage.rows, circuit.rows());
+
+      // One row fewer than the minimum runs out of rows.
+      let err = MockProver::run(minimal.k - 1, &circuit, circuit.instances(Fp::one(), Fp::one()));
+      assert!(err.is_err());
+    }
+
+    let circuit = FiboCircuit::<Fp>::new(100);
+    let minimal = minimal_k(&circuit, &circuit.instances(Fp::one(), Fp::one())).unwrap();
+    assert_eq!(minimal.bound, Bound::Rows);
+
+    let n = 1 << 20;
+    let circuit = FastFiboCircuit::<Fp>::new(21, n);
+    let instances = fibonacci_instances(SeedMode::Public, Fp::one(), Fp::one(), n as usize);
+    let minimal = minimal_k(&circuit, &instances).unwrap();
+    assert_eq!(minimal.k, circuit.k());
+    assert_eq!(minimal.usage.rows, circuit.rows());
+  }
+
+  #[test]
+  fn minimal_k_lookup_table_bound() {
+    let circuit = RangeCircuit::<Fp, 256>::new(Fp::from(255));
+    let minimal = minimal_k(&circuit, &circuit.instances()).unwrap();
+    assert_eq!(minimal.bound, Bound::LookupTable);
+    assert_eq!(minimal.usage.table_rows, 256);
+    assert_eq!(minimal.k, circuit.k());
+
+    // The polynomial range check has no table; a single row is set by the
+    // blinding factors.
+    let circuit = RangeCircuit::<Fp, 8>::new(Fp::from(7));
+    let minimal = minimal_k(&circuit, &circuit.instances()).unwrap();
+    assert_eq!(minimal.usage.table_rows, 0);
+    assert_eq!(minimal.bound, Bound::BlindingFactors);
+  }
+
+  #[test]
+  fn minimal_k_reports_failures() {
+    let circuit = RangeCircuit::<Fp, 8>::new(Fp::from(8));
+    let err = minimal_k(&circuit, &circuit.instances()).unwrap_err();
+    assert!(matches!(err, StatsError::Unsatisfied { ref failures, .. } if !failures.is_empty()));
+  }
+
+  #[test]
+  fn compare_stats() {
+    let n = 100;
+    let copy = FiboCircuit::<Fp>::new(n);
+    let rotation = FiboRotationCircuit::<Fp>::new(n);
+    let copy_stats = CircuitStats::measure(&copy, &copy.instances(Fp::one(), Fp::one())).unwrap();
+    let rotation_stats = CircuitStats::measure(&rotation, &rotation.instances(Fp::one(), Fp::one())).unwrap();
+
+    assert_eq!(copy_stats.advice_columns, 3);
+    assert_eq!(copy_stats.instance_columns, 1);
+    assert_eq!(copy_stats.selectors, 1);
+    assert_eq!(copy_stats.constraints, 1);
+    assert_eq!(copy_stats.lookups, 0);
+    assert_eq!(rotation_stats.advice_columns, 1);
+    assert_eq!(rotation_stats.usage.rows, n + 2);
+    assert_eq!(copy_stats.permutation_columns, 5);
+    assert_eq!(rotation_stats.permutation_columns, 3);
+    assert!(rotation_stats.proof.bytes < copy_stats.proof.bytes);
+    assert_eq!(copy_stats.verifier.evaluations, copy_stats.proof.evaluations);
+    assert!(copy_stats.verifier.msm_size > 1 << copy_stats.k);
+
+    let json: serde_json::Value = serde_json::from_str(&copy_stats.to_json()).unwrap();
+    assert_eq!(json["k"], copy_stats.k);
+    assert_eq!(json["bound"], "rows");
+    assert_eq!(json["usage"]["rows"], copy.rows());
+    assert_eq!(json["proof"]["bytes"], copy_stats.proof.bytes);
+    assert_eq!(json["permutation_columns"], 5);
+  }
+
+  #[test]
+  fn cost_fields_are_fallible() {
+    let debug = "Cost { k: 4, sizes: [A { commitments: 2 }, A { commitments: 3 }] }";
+    assert_eq!(cost_field(&Debugged(debug), "k").unwrap(), 4);
+    assert_eq!(cost_fields(&Debugged(debug), "commitments").unwrap(), vec![2, 3]);
+    assert!(matches!(cost_field(&Debugged(debug), "lookups"), Err(StatsError::CostFormat { field: "lookups" })));
+    assert!(matches!(cost_field(&Debugged("Cost { k: [4] }"), "k"), Err(StatsError::CostFormat { field: "k" })));
+  }
+
+  /// Debugs as the wrapped string.
+  struct Debugged(&'static str);
+
+  impl fmt::Debug for Debugged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      f.write_str(self.0)
+    }
+  }
+}